@@ -1,23 +1,58 @@
-use std::collections::VecDeque;
-use std::time::Duration;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use agent_stream_kit::{
-    ASKit, AgentContext, AgentData, AgentError, AgentOutput, AgentSpec, AgentValue, AsAgent,
+    ASKit, Agent, AgentContext, AgentData, AgentError, AgentOutput, AgentSpec, AgentValue, AsAgent,
     askit_agent, async_trait,
 };
-use im::{Vector, vector};
+use im::{HashMap, Vector, vector};
 use mini_moka::sync::Cache;
+use tokio::task::JoinHandle;
+
+use crate::data::{get_nested_value, parse_key_path};
+use crate::expr::Expr;
+use crate::index::resolve_slice;
+use crate::time::parse_duration_to_ms;
 
 const CATEGORY: &str = "Std/Array";
 
+const PIN_ACK: &str = "ack";
 const PIN_ARRAY: &str = "array";
+const PIN_FLUSH: &str = "flush";
+const PIN_OBJECT: &str = "object";
+const PIN_PROGRESS: &str = "progress";
 const PIN_IN1: &str = "in1";
 const PIN_IN2: &str = "in2";
+const PIN_MISSING: &str = "missing";
 const PIN_T: &str = "T";
 const PIN_F: &str = "F";
 const PIN_VALUE: &str = "value";
-
+const PIN_REJECTED: &str = "rejected";
+
+const CONFIG_BY: &str = "by";
+const CONFIG_COMPARE: &str = "compare";
+const CONFIG_DEPTH: &str = "depth";
+const CONFIG_EXPR: &str = "expr";
+const CONFIG_KEY: &str = "key";
+const CONFIG_LIMIT_BY: &str = "limit_by";
+const CONFIG_MAX_IN_FLIGHT: &str = "max_in_flight";
 const CONFIG_N: &str = "n";
+const CONFIG_NULLS: &str = "nulls";
+const CONFIG_OP: &str = "op";
+const CONFIG_ORDER: &str = "order";
+const CONFIG_PARTIAL: &str = "partial";
+const CONFIG_PERCENTILES: &str = "percentiles";
+const CONFIG_REDUCER: &str = "reducer";
+const CONFIG_SLICE: &str = "slice";
+const CONFIG_START: &str = "start";
+const CONFIG_STEP: &str = "step";
+const CONFIG_STOP: &str = "stop";
+const CONFIG_STRICT: &str = "strict";
+const CONFIG_TIME: &str = "time";
+const CONFIG_TIMEOUT: &str = "timeout";
+const CONFIG_TUMBLING: &str = "tumbling";
 const CONFIG_USE_CTX: &str = "use_ctx";
 const CONFIG_TTL_SEC: &str = "ttl_sec";
 const CONFIG_CAPACITY: &str = "capacity";
@@ -366,20 +401,20 @@ impl AsAgent for ArrayTakeAgent {
     }
 }
 
-/// Maps over an input array, emitting each item individually with a `map` frame that captures the index and length.
-/// Nested maps accumulate frames to preserve lineage. If the input is not an array, it is treated as a single-item array.
+/// Reverses the input array.
+/// If the input is not an array, outputs an array with the input as the only item.
 #[askit_agent(
-    title = "Map",
+    title = "ArrayReverse",
     category = CATEGORY,
     inputs = [PIN_ARRAY],
-    outputs = [PIN_VALUE],
+    outputs = [PIN_ARRAY],
 )]
-struct MapAgent {
+struct ArrayReverseAgent {
     data: AgentData,
 }
 
 #[async_trait]
-impl AsAgent for MapAgent {
+impl AsAgent for ArrayReverseAgent {
     fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
         let data = AgentData::new(askit, id, spec);
         Ok(Self { data })
@@ -391,64 +426,53 @@ impl AsAgent for MapAgent {
         _pin: String,
         value: AgentValue,
     ) -> Result<(), AgentError> {
-        match value {
-            AgentValue::Array(arr) => {
-                let n = arr.len();
-                for (i, item) in arr.into_iter().enumerate() {
-                    let c = ctx.push_map_frame(i, n)?;
-                    self.output(c, PIN_VALUE, item).await?;
-                }
-            }
-            other => {
-                let c = ctx.push_map_frame(0, 1)?;
-                self.output(c, PIN_VALUE, other).await?;
-            }
-        }
-        Ok(())
+        let reversed = match value {
+            AgentValue::Array(arr) => arr.into_iter().rev().collect(),
+            other => vector![other],
+        };
+        self.output(ctx, PIN_ARRAY, AgentValue::array(reversed))
+            .await
     }
 }
 
-/// Collects input values into an array.
-///
-/// Expects a `map` frame to determine the position and length for each input value.
-/// The `map` frame stores keys `i` (index) and `n` (length). Nested maps stack frames.
-/// If a `map` frame is not present, the input value is emitted directly.
-///
-/// Incomplete arrays are emitted when the context changes.
+/// Slices the input array with Python-like `start:end` notation.
+/// Negative indices count from the end and omitted bounds mean the start or end of the array,
+/// e.g. `-5:` takes the last five items and `:-1` drops the last item. Out-of-range bounds are clamped.
+/// If the input is not an array, it is treated as a single-item array.
 #[askit_agent(
-    title = "Collect",
+    title = "ArraySlice",
     category = CATEGORY,
-    description = "Collects input values into an array",
-    inputs = [PIN_VALUE],
+    inputs = [PIN_ARRAY],
     outputs = [PIN_ARRAY],
+    string_config(name = CONFIG_SLICE, default = ":", description = "start:end (ex. -5:, 1:3, :-1)"),
 )]
-struct CollectAgent {
+struct ArraySliceAgent {
     data: AgentData,
+    range: (Option<i64>, Option<i64>),
+}
 
-    // Records the context ID being processed to prevent other contexts from mixing
-    current_ctx_id: Option<usize>,
-
-    // Data buffer
-    input_values: Vec<Option<AgentValue>>,
-
-    // Expected size of the array
-    expected_size: usize,
-
-    // Number of items received (counter to avoid scanning input_values every time)
-    received_count: usize,
+impl ArraySliceAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<(Option<i64>, Option<i64>), AgentError> {
+        let slice = spec
+            .configs
+            .as_ref()
+            .map(|cfg| cfg.get_string_or(CONFIG_SLICE, ":"))
+            .unwrap_or_else(|| ":".to_string());
+        parse_slice(&slice)
+    }
 }
 
 #[async_trait]
-impl AsAgent for CollectAgent {
-    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+impl AsAgent for ArraySliceAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let range = Self::update_spec(&mut spec)?;
         let data = AgentData::new(askit, id, spec);
-        Ok(Self {
-            data,
-            current_ctx_id: None,
-            input_values: Vec::new(),
-            expected_size: 0,
-            received_count: 0,
-        })
+        Ok(Self { data, range })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        self.range = Self::update_spec(&mut self.data.spec)?;
+        Ok(())
     }
 
     async fn process(
@@ -457,225 +481,140 @@ impl AsAgent for CollectAgent {
         _pin: String,
         value: AgentValue,
     ) -> Result<(), AgentError> {
-        // Check for map frame
-        // If not within a map, pass the value through as-is.
-        let Some((idx, n)) = ctx.current_map_frame()? else {
-            return self.output(ctx, PIN_ARRAY, value).await;
+        let arr = match value {
+            AgentValue::Array(arr) => arr,
+            other => vector![other],
         };
-
-        // Detect context switch and flush processing
-        // If a new context ID arrives while the previous context hasn't finished processing
-        let ctx_id = ctx.id();
-        if let Some(last_id) = &self.current_ctx_id {
-            if last_id != &ctx_id {
-                log::warn!("Context changed before collection completed. Dropping partial data.");
-                self.reset_state();
-            }
-        }
-
-        // Initialize state (when the first item of this context arrives)
-        if self.input_values.is_empty() {
-            self.current_ctx_id = Some(ctx_id);
-            self.expected_size = n;
-            // Fill with None for the required size
-            self.input_values = vec![None; n];
-            self.received_count = 0;
-        }
-
-        // Validation
-        if n != self.expected_size {
-            // Size shouldn't change within the same context ID, but check just in case
-            return Err(AgentError::InvalidValue(
-                "Map frame size mismatch within the same context".into(),
-            ));
-        }
-        if idx >= n {
-            return Err(AgentError::InvalidValue(
-                "Map frame index is out of bounds".into(),
-            ));
-        }
-
-        // Store data
-        // Check if attempting to write to a position that's already filled (duplicate index)
-        if self.input_values[idx].is_some() {
-            // If duplicate data arrives, overwrite (could also error instead).
-        } else {
-            self.received_count += 1;
-        }
-        self.input_values[idx] = Some(value);
-
-        // Check for completion
-        if self.received_count == self.expected_size {
-            // All items collected, output the result
-            let arr = self.drain_buffer_to_vector();
-
-            // Reset state
-            self.reset_state();
-
-            // Pop one map frame and output
-            let next_ctx = ctx.pop_map_frame()?;
-            self.output(next_ctx, PIN_ARRAY, AgentValue::array(arr)).await
+        let (start, end) = resolve_slice(arr.len(), self.range.0, self.range.1);
+        let sliced = if start < end {
+            arr.skip(start).take(end - start)
         } else {
-            // Not yet complete, keep waiting
-            Ok(())
-        }
+            Vector::new()
+        };
+        self.output(ctx, PIN_ARRAY, AgentValue::array(sliced)).await
     }
 }
 
-impl CollectAgent {
-    fn reset_state(&mut self) {
-        self.current_ctx_id = None;
-        self.input_values.clear(); // Capacity is preserved for efficient reuse
-        self.expected_size = 0;
-        self.received_count = 0;
+/// Flattens nested arrays in the input array up to `depth` levels.
+/// A negative depth flattens completely. Non-array items are kept as they are.
+/// If the input is not an array, outputs an array with the input as the only item.
+#[askit_agent(
+    title = "ArrayFlatten",
+    category = CATEGORY,
+    inputs = [PIN_ARRAY],
+    outputs = [PIN_ARRAY],
+    integer_config(name = CONFIG_DEPTH, default = 1, description = "-1: flatten completely"),
+)]
+struct ArrayFlattenAgent {
+    data: AgentData,
+}
+
+#[async_trait]
+impl AsAgent for ArrayFlattenAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self { data })
     }
 
-    // Drain the buffer contents and convert to im::Vector
-    fn drain_buffer_to_vector(&mut self) -> Vector<AgentValue> {
-        self.input_values
-            .drain(..)
-            .map(|v| v.unwrap_or(AgentValue::Unit)) // Fill missing values with Unit
-            .collect()
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let depth = self
+            .data
+            .spec
+            .configs
+            .as_ref()
+            .map(|cfg| cfg.get_integer_or(CONFIG_DEPTH, 1))
+            .unwrap_or(1);
+
+        let arr = match value {
+            AgentValue::Array(arr) => arr,
+            other => vector![other],
+        };
+        let mut flat = Vector::new();
+        flatten_into(&mut flat, arr, depth);
+        self.output(ctx, PIN_ARRAY, AgentValue::array(flat)).await
     }
 }
 
-/// Zips multiple inputs into an array.
-///
-/// The number of inputs n is specified via configuration.
-///
-/// If n=2, it takes two inputs: in1 and in2. Once all inputs are present,
-/// it emits them as [in1, in2].
-///
-/// If in2 arrives repeatedly before in1, the in2 values are queued; when in1 arrives,
-/// they’re paired in order from the head of the queue and emitted.
+/// Concatenates arrays arriving on n inputs (in1, in2, ...) into a single array.
 ///
-/// When the `use_ctx` config is true, inputs are matched by context key (including map frames)
-/// so that mapped items zip correctly even when they interleave.
+/// Once every input has a value, they are concatenated in pin order and emitted.
+/// Inputs are paired like ZipToArray: values arriving repeatedly on the same pin are queued,
+/// or matched by context key with `use_ctx`.
+/// Non-array inputs are treated as single-item arrays.
 #[askit_agent(
-    title = "ZipToArray",
+    title = "ArrayConcat",
     category = CATEGORY,
     inputs = [PIN_IN1, PIN_IN2],
     outputs = [PIN_ARRAY],
     integer_config(name = CONFIG_N, default = 2),
     boolean_config(name = CONFIG_USE_CTX),
-    integer_config(name = CONFIG_TTL_SEC, default = 60), 
+    integer_config(name = CONFIG_TTL_SEC, default = 60),
     integer_config(name = CONFIG_CAPACITY, default = 1000),
 )]
-struct ZipToArrayAgent {
+struct ArrayConcatAgent {
     data: AgentData,
     n: usize,
     use_ctx: bool,
-
     ttl_sec: u64,
     capacity: u64,
-    queues: Vec<VecDeque<AgentValue>>, // for non-ctx mode
-
-    // Context Key -> PendingZip
-    ctx_buffers: Cache<String, PendingZip>,
-}
-
-#[derive(Clone)]
-struct PendingZip {
-    values: Vec<Option<AgentValue>>,
-    count: usize,
+    zip: ZipBuffer,
 }
 
-impl ZipToArrayAgent {
+impl ArrayConcatAgent {
     fn update_spec(spec: &mut AgentSpec) -> Result<(usize, bool, u64, u64), AgentError> {
-        let mut n = spec
+        let n = spec
             .configs
             .as_ref()
             .map(|cfg| cfg.get_integer_or(CONFIG_N, 2))
-            .unwrap_or(2) as usize;
-        if n < 1 {
-            n = 1;
-        }
-
-        let use_ctx = spec
-            .configs
-            .as_ref()
-            .map(|cfg| cfg.get_bool_or_default(CONFIG_USE_CTX))
-            .unwrap_or(false);
-
-        let ttl_sec = spec
-            .configs
-            .as_ref()
-            .map(|c| c.get_integer_or(CONFIG_TTL_SEC, 60))
-            .unwrap_or(60) as u64;
+            .unwrap_or(2)
+            .max(1) as usize;
 
-        let capacity = spec
-            .configs
-            .as_ref()
-            .map(|c| c.get_integer_or(CONFIG_CAPACITY, 1000))
-            .unwrap_or(1000) as u64;
+        let (use_ctx, ttl_sec, capacity) = zip_configs(spec);
 
         spec.inputs = Some((1..=n).map(|i| format!("in{}", i)).collect());
 
         Ok((n, use_ctx, ttl_sec, capacity))
     }
-
-    fn reset_state(&mut self) {
-        self.queues = vec![VecDeque::new(); self.n];
-        self.ctx_buffers.invalidate_all();
-    }
 }
 
 #[async_trait]
-impl AsAgent for ZipToArrayAgent {
+impl AsAgent for ArrayConcatAgent {
     fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
         let (n, use_ctx, ttl_sec, capacity) = Self::update_spec(&mut spec)?;
-
-        let cache = Cache::builder()
-            .max_capacity(capacity) // Capacity limit (oldest entries are evicted on overflow)
-            .time_to_live(Duration::from_secs(ttl_sec)) // TTL (entries expire X seconds after write)
-            .build();
-
         let data = AgentData::new(askit, id, spec);
-
         Ok(Self {
             data,
             n,
             use_ctx,
             ttl_sec,
             capacity,
-            queues: vec![VecDeque::new(); n],
-            ctx_buffers: cache,
+            zip: ZipBuffer::new(n, use_ctx, ttl_sec, capacity),
         })
     }
 
     fn configs_changed(&mut self) -> Result<(), AgentError> {
         let (n, use_ctx, ttl_sec, capacity) = Self::update_spec(&mut self.data.spec)?;
-        let mut changed = false;
-        if n != self.n {
+        if (n, use_ctx, ttl_sec, capacity) != (self.n, self.use_ctx, self.ttl_sec, self.capacity) {
+            let n_changed = n != self.n;
             self.n = n;
-            changed = true;
-        }
-        if use_ctx != self.use_ctx {
             self.use_ctx = use_ctx;
-            changed = true;
-        }
-        if ttl_sec != self.ttl_sec {
             self.ttl_sec = ttl_sec;
-            changed = true;
-        }
-        if capacity != self.capacity {
             self.capacity = capacity;
-            changed = true;
-        }
-        if changed {
-            self.reset_state();
-            // Rebuild cache with new capacity and TTL
-            self.ctx_buffers = Cache::builder()
-                .max_capacity(capacity)
-                .time_to_live(Duration::from_secs(ttl_sec))
-                .build();
-            self.emit_agent_spec_updated();
+            self.zip = ZipBuffer::new(n, use_ctx, ttl_sec, capacity);
+            if n_changed {
+                self.emit_agent_spec_updated();
+            }
         }
         Ok(())
     }
 
     async fn stop(&mut self) -> Result<(), AgentError> {
-        self.reset_state();
+        self.zip.reset();
         Ok(())
     }
 
@@ -685,63 +624,3409 @@ impl AsAgent for ZipToArrayAgent {
         pin: String,
         value: AgentValue,
     ) -> Result<(), AgentError> {
-        // Parse pin number
-        let Some(idx) = pin
-            .strip_prefix("in")
-            .and_then(|s| s.parse::<usize>().ok())
-            .filter(|&i| i >= 1 && i <= self.n)
-            .map(|i| i - 1)
-        else {
-            return Err(AgentError::InvalidValue(format!(
-                "Invalid input pin: {}",
-                pin
-            )));
+        let Some(inputs) = self.zip.push(&ctx, &pin, value)? else {
+            return Ok(());
         };
 
-        if self.use_ctx {
-            let ctx_key = ctx.ctx_key()?;
-
-            // Get from cache (or create new if not present)
-            let mut entry = self.ctx_buffers.get(&ctx_key).unwrap_or_else(|| PendingZip {
-                values: vec![None; self.n],
-                count: 0,
-            });
-
-            // Update
-            if entry.values[idx].is_none() {
-                entry.count += 1;
+        let mut arr = Vector::new();
+        for input in inputs {
+            match input {
+                AgentValue::Array(items) => arr.append(items),
+                other => arr.push_back(other),
             }
-            entry.values[idx] = Some(value);
+        }
+        self.output(ctx, PIN_ARRAY, AgentValue::array(arr)).await
+    }
+}
 
-            // Check for completion
-            if entry.count == self.n {
-                // All inputs collected, remove from cache (invalidate)
-                self.ctx_buffers.invalidate(&ctx_key);
+/// Emits an array of numbers from `start` up to (but not including) `stop`, counting by `step`.
+///
+/// The items are integers if start, stop and step are all whole numbers, and numbers otherwise.
+/// A negative step counts down. Any input triggers the output; if the input is an object,
+/// its `start`, `stop` and `step` fields override the configs, e.g. for pagination.
+#[askit_agent(
+    title = "ArrayRange",
+    category = CATEGORY,
+    inputs = [PIN_VALUE],
+    outputs = [PIN_ARRAY],
+    number_config(name = CONFIG_START, default = 0.0),
+    number_config(name = CONFIG_STOP, default = 10.0),
+    number_config(name = CONFIG_STEP, default = 1.0),
+)]
+struct ArrayRangeAgent {
+    data: AgentData,
+}
 
-                let arr: Vector<AgentValue> = entry.values
-                    .into_iter()
-                    .map(|v| v.unwrap())
-                    .collect();
+#[async_trait]
+impl AsAgent for ArrayRangeAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+        })
+    }
 
-                return self.output(ctx, PIN_ARRAY, AgentValue::array(arr)).await;
-            }
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let config = self.configs()?;
+        let param = |name: &str, default: f64| {
+            value
+                .get(name)
+                .and_then(|v| v.as_f64())
+                .unwrap_or_else(|| config.get_number_or(name, default))
+        };
+        let start = param(CONFIG_START, 0.0);
+        let stop = param(CONFIG_STOP, 10.0);
+        let step = param(CONFIG_STEP, 1.0);
 
-            return Ok(());
-        }
+        let arr = range_array(start, stop, step)?;
+        self.output(ctx, PIN_ARRAY, AgentValue::array(arr)).await
+    }
+}
 
-        // Simple FIFO mode processing
-        self.queues[idx].push_back(value);
+/// Emits an array holding the input value n times. n is capped like the length of ArrayRange.
+#[askit_agent(
+    title = "ArrayRepeat",
+    category = CATEGORY,
+    inputs = [PIN_VALUE],
+    outputs = [PIN_ARRAY],
+    integer_config(name = CONFIG_N, default = 2),
+)]
+struct ArrayRepeatAgent {
+    data: AgentData,
+}
 
-        // Check if all queues have data
-        if self.queues.iter().all(|q| !q.is_empty()) {
-            let arr: Vector<AgentValue> = self.queues
-                .iter_mut()
-                .map(|q| q.pop_front().unwrap())
-                .collect();
+#[async_trait]
+impl AsAgent for ArrayRepeatAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+        })
+    }
 
-            self.output(ctx, PIN_ARRAY, AgentValue::array(arr)).await
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let n = self.configs()?.get_integer_or(CONFIG_N, 2).max(0) as usize;
+        let arr = repeat_array(value, n)?;
+        self.output(ctx, PIN_ARRAY, AgentValue::array(arr)).await
+    }
+}
+
+/// Transposes rows and columns of tabular data.
+///
+/// - An array of arrays is transposed, e.g. `[[a1, a2], [b1, b2]]` to `[[a1, b1], [a2, b2]]`.
+/// - An array of objects (records) becomes an object of arrays (columns) with one array per key.
+/// - An object of arrays (columns) becomes an array of objects (records).
+///
+/// Ragged input (rows or columns of different lengths, records with different keys) is an error in
+/// `strict` mode. Otherwise, missing cells are null, except that records omit missing keys.
+#[askit_agent(
+    title = "ArrayTranspose",
+    category = CATEGORY,
+    inputs = [PIN_VALUE],
+    outputs = [PIN_VALUE],
+    boolean_config(name = CONFIG_STRICT),
+)]
+struct ArrayTransposeAgent {
+    data: AgentData,
+}
+
+#[async_trait]
+impl AsAgent for ArrayTransposeAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+        })
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let strict = self.configs()?.get_bool_or_default(CONFIG_STRICT);
+        let transposed = transpose(value, strict)?;
+        self.output(ctx, PIN_VALUE, transposed).await
+    }
+}
+
+/// Sorts the input array by the value at a dotted key path (the item itself if the key is empty).
+///
+/// - `order`: `asc` or `desc`
+/// - `compare`: `auto` (numbers numerically, strings lexicographically, otherwise by type),
+///   `numeric`, `lexical` or `natural` (digit runs compared as numbers, e.g. `file2 < file10`)
+/// - `nulls`: `first` or `last`; missing keys and values that cannot be compared in the chosen mode are nulls
+///
+/// Sorting is stable. If the input is not an array, it is treated as a single-item array.
+#[askit_agent(
+    title = "ArraySort",
+    category = CATEGORY,
+    inputs = [PIN_ARRAY],
+    outputs = [PIN_ARRAY],
+    string_config(name = CONFIG_KEY),
+    string_config(name = CONFIG_ORDER, default = "asc", description = "asc or desc"),
+    string_config(name = CONFIG_COMPARE, default = "auto", description = "auto, numeric, lexical or natural"),
+    string_config(name = CONFIG_NULLS, default = "last", description = "first or last"),
+)]
+struct ArraySortAgent {
+    data: AgentData,
+    options: SortOptions,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CompareMode {
+    Auto,
+    Numeric,
+    Lexical,
+    Natural,
+}
+
+struct SortOptions {
+    keys: Vec<String>,
+    desc: bool,
+    compare: CompareMode,
+    nulls_first: bool,
+}
+
+impl ArraySortAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<SortOptions, AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let key_str = cfg.get_string_or_default(CONFIG_KEY);
+        let keys = parse_key_path(&key_str);
+
+        let desc = match cfg.get_string_or(CONFIG_ORDER, "asc").as_str() {
+            "asc" => false,
+            "desc" => true,
+            other => {
+                return Err(AgentError::InvalidConfig(format!(
+                    "order must be asc or desc: {}",
+                    other
+                )));
+            }
+        };
+
+        let compare = match cfg.get_string_or(CONFIG_COMPARE, "auto").as_str() {
+            "auto" => CompareMode::Auto,
+            "numeric" => CompareMode::Numeric,
+            "lexical" => CompareMode::Lexical,
+            "natural" => CompareMode::Natural,
+            other => {
+                return Err(AgentError::InvalidConfig(format!(
+                    "compare must be auto, numeric, lexical or natural: {}",
+                    other
+                )));
+            }
+        };
+
+        let nulls_first = match cfg.get_string_or(CONFIG_NULLS, "last").as_str() {
+            "first" => true,
+            "last" => false,
+            other => {
+                return Err(AgentError::InvalidConfig(format!(
+                    "nulls must be first or last: {}",
+                    other
+                )));
+            }
+        };
+
+        Ok(SortOptions {
+            keys,
+            desc,
+            compare,
+            nulls_first,
+        })
+    }
+}
+
+#[async_trait]
+impl AsAgent for ArraySortAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let options = Self::update_spec(&mut spec)?;
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self { data, options })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        self.options = Self::update_spec(&mut self.data.spec)?;
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let arr = match value {
+            AgentValue::Array(arr) => arr,
+            other => vector![other],
+        };
+
+        let opts = &self.options;
+        let mut items: Vec<(Option<AgentValue>, AgentValue)> = arr
+            .into_iter()
+            .map(|item| {
+                let key =
+                    get_nested_value(&item, &opts.keys).and_then(|k| sort_key(k, opts.compare));
+                (key, item)
+            })
+            .collect();
+
+        // Vec::sort_by is stable, and reversing the ordering keeps equal items in input order.
+        items.sort_by(|(a, _), (b, _)| match (a, b) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) if opts.nulls_first => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) if opts.nulls_first => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) => {
+                let ord = compare_sort_keys(a, b, opts.compare);
+                if opts.desc { ord.reverse() } else { ord }
+            }
+        });
+
+        let sorted: Vector<AgentValue> = items.into_iter().map(|(_, item)| item).collect();
+        self.output(ctx, PIN_ARRAY, AgentValue::array(sorted)).await
+    }
+}
+
+/// Filters an input array by a predicate expression.
+///
+/// The expression is evaluated for each item with `value` bound to the item and `index` to its position,
+/// e.g. `value.score > 0.5 && value.lang == "en"`. Items for which it is truthy are emitted on `array`,
+/// the others on `rejected`, both in their original order.
+/// If the input is not an array, it is treated as a single-item array.
+#[askit_agent(
+    title = "ArrayFilter",
+    category = CATEGORY,
+    inputs = [PIN_ARRAY],
+    outputs = [PIN_ARRAY, PIN_REJECTED],
+    string_config(name = CONFIG_EXPR, default = "value"),
+)]
+struct ArrayFilterAgent {
+    data: AgentData,
+    expr: Expr,
+}
+
+impl ArrayFilterAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<Expr, AgentError> {
+        let expr_str = spec
+            .configs
+            .as_ref()
+            .map(|cfg| cfg.get_string_or(CONFIG_EXPR, "value"))
+            .unwrap_or_else(|| "value".to_string());
+        Expr::parse(&expr_str)
+    }
+}
+
+#[async_trait]
+impl AsAgent for ArrayFilterAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let expr = Self::update_spec(&mut spec)?;
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self { data, expr })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        self.expr = Self::update_spec(&mut self.data.spec)?;
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let arr = match value {
+            AgentValue::Array(arr) => arr,
+            other => vector![other],
+        };
+
+        let mut kept = Vector::new();
+        let mut rejected = Vector::new();
+        for (i, item) in arr.into_iter().enumerate() {
+            let index = AgentValue::integer(i as i64);
+            if self.expr.test(&[("value", &item), ("index", &index)]) {
+                kept.push_back(item);
+            } else {
+                rejected.push_back(item);
+            }
+        }
+
+        self.output(ctx.clone(), PIN_ARRAY, AgentValue::array(kept))
+            .await?;
+        self.output(ctx, PIN_REJECTED, AgentValue::array(rejected))
+            .await
+    }
+}
+
+/// Groups the items of the input array by the value at a dotted key path.
+///
+/// Outputs an object mapping each distinct key to the array of items having that key, in input order.
+/// String keys are used as-is, other scalars are stringified and arrays or objects are grouped under their
+/// JSON representation. Items whose key is missing or null are sent to `missing` instead, so they never mix
+/// with a real `"null"` string key.
+/// If the input is not an array, it is treated as a single-item array.
+#[askit_agent(
+    title = "ArrayGroupBy",
+    category = CATEGORY,
+    inputs = [PIN_ARRAY],
+    outputs = [PIN_OBJECT, PIN_MISSING],
+    string_config(name = CONFIG_KEY),
+)]
+struct ArrayGroupByAgent {
+    data: AgentData,
+    target_keys: Vec<String>,
+}
+
+impl ArrayGroupByAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<Vec<String>, AgentError> {
+        let key_str = spec
+            .configs
+            .as_ref()
+            .map(|cfg| cfg.get_string_or_default(CONFIG_KEY))
+            .unwrap_or_default();
+        Ok(parse_key_path(&key_str))
+    }
+}
+
+#[async_trait]
+impl AsAgent for ArrayGroupByAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let target_keys = Self::update_spec(&mut spec)?;
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self { data, target_keys })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        self.target_keys = Self::update_spec(&mut self.data.spec)?;
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let arr = match value {
+            AgentValue::Array(arr) => arr,
+            other => vector![other],
+        };
+
+        let (groups, missing) = group_by(arr, &self.target_keys);
+        self.output(ctx.clone(), PIN_OBJECT, AgentValue::object(groups))
+            .await?;
+        self.output(ctx, PIN_MISSING, AgentValue::array(missing))
+            .await
+    }
+}
+
+/// Partitions the input array into an array of arrays.
+///
+/// - `by = expr`: splits into `[matching, rest]` using a predicate expression over `value` and `index`
+///   (same syntax as ArrayFilter).
+/// - `by = count`: splits into `n` contiguous buckets of near-equal size; earlier buckets receive the remainder.
+///   `n` is limited to 1,000,000 buckets.
+///
+/// If the input is not an array, it is treated as a single-item array.
+#[askit_agent(
+    title = "ArrayPartition",
+    category = CATEGORY,
+    inputs = [PIN_ARRAY],
+    outputs = [PIN_ARRAY],
+    string_config(name = CONFIG_BY, default = "expr", description = "expr or count"),
+    string_config(name = CONFIG_EXPR, default = "value"),
+    integer_config(name = CONFIG_N, default = 2),
+)]
+struct ArrayPartitionAgent {
+    data: AgentData,
+    partition: Partition,
+}
+
+enum Partition {
+    Expr(Expr),
+    Count(usize),
+}
+
+impl ArrayPartitionAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<Partition, AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+        match cfg.get_string_or(CONFIG_BY, "expr").as_str() {
+            "expr" => {
+                let expr = Expr::parse(&cfg.get_string_or(CONFIG_EXPR, "value"))?;
+                Ok(Partition::Expr(expr))
+            }
+            "count" => {
+                let n = cfg.get_integer_or(CONFIG_N, 2);
+                if n < 1 {
+                    return Err(AgentError::InvalidConfig("n must be positive".into()));
+                }
+                if n as f64 > MAX_RANGE_LEN {
+                    return Err(AgentError::InvalidConfig(format!(
+                        "n {} exceeds the maximum of {}",
+                        n, MAX_RANGE_LEN
+                    )));
+                }
+                Ok(Partition::Count(n as usize))
+            }
+            other => Err(AgentError::InvalidConfig(format!(
+                "by must be expr or count: {}",
+                other
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl AsAgent for ArrayPartitionAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let partition = Self::update_spec(&mut spec)?;
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self { data, partition })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        self.partition = Self::update_spec(&mut self.data.spec)?;
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let arr = match value {
+            AgentValue::Array(arr) => arr,
+            other => vector![other],
+        };
+
+        let buckets: Vector<AgentValue> = match &self.partition {
+            Partition::Expr(expr) => {
+                let mut matched = Vector::new();
+                let mut rest = Vector::new();
+                for (i, item) in arr.into_iter().enumerate() {
+                    let index = AgentValue::integer(i as i64);
+                    if expr.test(&[("value", &item), ("index", &index)]) {
+                        matched.push_back(item);
+                    } else {
+                        rest.push_back(item);
+                    }
+                }
+                vector![AgentValue::array(matched), AgentValue::array(rest)]
+            }
+            Partition::Count(n) => partition_count(arr, *n),
+        };
+
+        self.output(ctx, PIN_ARRAY, AgentValue::array(buckets))
+            .await
+    }
+}
+
+/// Computes summary statistics of the numbers in the input array.
+///
+/// Items are read at a dotted key path, or used as they are if the key is empty; non-numeric values are skipped.
+/// Outputs an object with `count`, `sum`, `mean`, `min`, `max`, `median`, `variance` and `stddev`
+/// (population), plus a `p<N>` entry for each configured percentile (linear interpolation),
+/// with `_` in place of a decimal point (`99.9` is `p99_9`).
+/// Statistics other than `count` and `sum` are null for an empty input.
+/// If the input is not an array, it is treated as a single-item array.
+#[askit_agent(
+    title = "ArrayStats",
+    category = CATEGORY,
+    inputs = [PIN_ARRAY],
+    outputs = [PIN_OBJECT],
+    string_config(name = CONFIG_KEY),
+    string_config(name = CONFIG_PERCENTILES, default = "25,75,90,99", description = "comma-separated, 0 to 100"),
+)]
+struct ArrayStatsAgent {
+    data: AgentData,
+    target_keys: Vec<String>,
+    percentiles: Vec<f64>,
+}
+
+impl ArrayStatsAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<(Vec<String>, Vec<f64>), AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let key_str = cfg.get_string_or_default(CONFIG_KEY);
+        let target_keys = parse_key_path(&key_str);
+
+        let percentiles = parse_percentiles(&cfg.get_string_or(CONFIG_PERCENTILES, "25,75,90,99"))?;
+
+        Ok((target_keys, percentiles))
+    }
+}
+
+#[async_trait]
+impl AsAgent for ArrayStatsAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (target_keys, percentiles) = Self::update_spec(&mut spec)?;
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self {
+            data,
+            target_keys,
+            percentiles,
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        (self.target_keys, self.percentiles) = Self::update_spec(&mut self.data.spec)?;
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let arr = match value {
+            AgentValue::Array(arr) => arr,
+            other => vector![other],
+        };
+
+        let numbers: Vec<f64> = arr
+            .iter()
+            .filter_map(|item| get_nested_value(item, &self.target_keys)?.as_f64())
+            .filter(|x| !x.is_nan())
+            .collect();
+
+        let stats = array_stats(numbers, &self.percentiles)?;
+        self.output(ctx, PIN_OBJECT, stats).await
+    }
+}
+
+/// Removes duplicate items from the input array, keeping the first occurrence of each.
+/// Items are compared by the value at a dotted key path, or as whole values if the key is empty.
+/// If the input is not an array, it is treated as a single-item array.
+#[askit_agent(
+    title = "ArrayUnique",
+    category = CATEGORY,
+    inputs = [PIN_ARRAY],
+    outputs = [PIN_ARRAY],
+    string_config(name = CONFIG_KEY),
+)]
+struct ArrayUniqueAgent {
+    data: AgentData,
+    target_keys: Vec<String>,
+}
+
+impl ArrayUniqueAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<Vec<String>, AgentError> {
+        let key_str = spec
+            .configs
+            .as_ref()
+            .map(|cfg| cfg.get_string_or_default(CONFIG_KEY))
+            .unwrap_or_default();
+        Ok(parse_key_path(&key_str))
+    }
+}
+
+#[async_trait]
+impl AsAgent for ArrayUniqueAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let target_keys = Self::update_spec(&mut spec)?;
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self { data, target_keys })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        self.target_keys = Self::update_spec(&mut self.data.spec)?;
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let arr = match value {
+            AgentValue::Array(arr) => arr,
+            other => vector![other],
+        };
+
+        let mut seen = HashSet::new();
+        let unique: Vector<AgentValue> = arr
+            .into_iter()
+            .filter(|item| seen.insert(identity_key(get_nested_value(item, &self.target_keys))))
+            .collect();
+
+        self.output(ctx, PIN_ARRAY, AgentValue::array(unique)).await
+    }
+}
+
+/// Suppresses values that have already been seen within the TTL window.
+///
+/// Values are compared by the value at a dotted key path, or as whole values if the key is empty.
+/// A value is emitted the first time its key is seen; the key is then remembered for `ttl_sec` seconds,
+/// up to `capacity` keys (the oldest are forgotten first).
+#[askit_agent(
+    title = "Distinct",
+    category = CATEGORY,
+    inputs = [PIN_VALUE],
+    outputs = [PIN_VALUE],
+    string_config(name = CONFIG_KEY),
+    integer_config(name = CONFIG_TTL_SEC, default = 60),
+    integer_config(name = CONFIG_CAPACITY, default = 1000),
+)]
+struct DistinctAgent {
+    data: AgentData,
+    target_keys: Vec<String>,
+    ttl_sec: u64,
+    capacity: u64,
+
+    // Identity Key -> ()
+    seen: Cache<String, ()>,
+}
+
+impl DistinctAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<(Vec<String>, u64, u64), AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let key_str = cfg.get_string_or_default(CONFIG_KEY);
+        let target_keys = parse_key_path(&key_str);
+
+        let ttl_sec = cfg.get_integer_or(CONFIG_TTL_SEC, 60) as u64;
+        let capacity = cfg.get_integer_or(CONFIG_CAPACITY, 1000) as u64;
+
+        Ok((target_keys, ttl_sec, capacity))
+    }
+}
+
+#[async_trait]
+impl AsAgent for DistinctAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (target_keys, ttl_sec, capacity) = Self::update_spec(&mut spec)?;
+
+        let cache = Cache::builder()
+            .max_capacity(capacity)
+            .time_to_live(Duration::from_secs(ttl_sec))
+            .build();
+
+        let data = AgentData::new(askit, id, spec);
+
+        Ok(Self {
+            data,
+            target_keys,
+            ttl_sec,
+            capacity,
+            seen: cache,
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        let (target_keys, ttl_sec, capacity) = Self::update_spec(&mut self.data.spec)?;
+        if target_keys != self.target_keys {
+            self.target_keys = target_keys;
+            self.seen.invalidate_all();
+        }
+        if ttl_sec != self.ttl_sec || capacity != self.capacity {
+            self.ttl_sec = ttl_sec;
+            self.capacity = capacity;
+            // Rebuild cache with new capacity and TTL
+            self.seen = Cache::builder()
+                .max_capacity(capacity)
+                .time_to_live(Duration::from_secs(ttl_sec))
+                .build();
+        }
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), AgentError> {
+        self.seen.invalidate_all();
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let key = identity_key(get_nested_value(&value, &self.target_keys));
+        if self.seen.contains_key(&key) {
+            return Ok(());
+        }
+        self.seen.insert(key, ());
+        self.output(ctx, PIN_VALUE, value).await
+    }
+}
+
+/// Groups streamed values into arrays.
+///
+/// Values are buffered and emitted as an array when `n` values have been buffered, when `time` has
+/// elapsed since the first value of the batch arrived, when any value arrives on `flush`, or when the
+/// agent stops. Batches are emitted with the context of their first value. Empty batches are never emitted.
+#[askit_agent(
+    title = "Batch",
+    category = CATEGORY,
+    inputs = [PIN_VALUE, PIN_FLUSH],
+    outputs = [PIN_ARRAY],
+    integer_config(name = CONFIG_N, default = 10, description = "0: no size limit"),
+    string_config(name = CONFIG_TIME, description = "(ex. 10s, 500ms) empty: no time limit"),
+)]
+struct BatchAgent {
+    data: AgentData,
+    n: usize,
+    time_ms: Option<u64>,
+
+    // Batch in progress, shared with the timer task
+    state: Arc<Mutex<BatchState>>,
+}
+
+#[derive(Default)]
+struct BatchState {
+    // Context of the first value in the batch
+    ctx: Option<AgentContext>,
+    items: Vector<AgentValue>,
+
+    // Incremented for every new batch so that a stale timer never flushes a newer one
+    seq: u64,
+}
+
+impl BatchState {
+    fn take(&mut self) -> Option<(AgentContext, Vector<AgentValue>)> {
+        let ctx = self.ctx.take()?;
+        Some((ctx, std::mem::take(&mut self.items)))
+    }
+}
+
+impl BatchAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<(usize, Option<u64>), AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let n = cfg.get_integer_or(CONFIG_N, 10).max(0) as usize;
+
+        let time = cfg.get_string_or_default(CONFIG_TIME);
+        let time_ms = if time.trim().is_empty() {
+            None
+        } else {
+            Some(parse_duration_to_ms(&time)?)
+        };
+
+        Ok((n, time_ms))
+    }
+
+    // Flush the batch with seq after the time limit unless it has been flushed in the meantime
+    fn start_timer(&self, seq: u64, time_ms: u64) {
+        let state = self.state.clone();
+        let askit = self.askit().clone();
+        let agent_id = self.id().to_string();
+
+        self.runtime().spawn(async move {
+            tokio::time::sleep(Duration::from_millis(time_ms)).await;
+
+            let batch = {
+                let mut state = state.lock().unwrap();
+                if state.seq != seq {
+                    return;
+                }
+                state.take()
+            };
+
+            if let Some((ctx, items)) = batch
+                && let Err(e) = askit.try_send_agent_out(
+                    agent_id,
+                    ctx,
+                    PIN_ARRAY.to_string(),
+                    AgentValue::array(items),
+                )
+            {
+                log::error!("Failed to send batch: {}", e);
+            }
+        });
+    }
+
+    async fn flush(&mut self) -> Result<(), AgentError> {
+        let batch = self.state.lock().unwrap().take();
+        if let Some((ctx, items)) = batch {
+            self.output(ctx, PIN_ARRAY, AgentValue::array(items))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AsAgent for BatchAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (n, time_ms) = Self::update_spec(&mut spec)?;
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self {
+            data,
+            n,
+            time_ms,
+            state: Default::default(),
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        let (n, time_ms) = Self::update_spec(&mut self.data.spec)?;
+        self.n = n;
+        self.time_ms = time_ms;
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), AgentError> {
+        self.flush().await
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        if pin == PIN_FLUSH {
+            return self.flush().await;
+        }
+
+        let full = {
+            let mut state = self.state.lock().unwrap();
+            if state.ctx.is_none() {
+                // First value of a new batch
+                state.ctx = Some(ctx);
+                state.seq += 1;
+                if let Some(time_ms) = self.time_ms {
+                    self.start_timer(state.seq, time_ms);
+                }
+            }
+            state.items.push_back(value);
+            self.n > 0 && state.items.len() >= self.n
+        };
+
+        if full {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Emits windows of streamed values as arrays.
+///
+/// A window holds at most `capacity` values (0: no limit) received within the last `ttl_sec` seconds
+/// (0: no limit).
+///
+/// - Sliding (default): every `step` values, the current window is emitted with the context of the latest value.
+///   With `capacity = 5` and `step = 1`, each value emits the last five values.
+/// - Tumbling: windows do not overlap. A window is emitted and cleared once it holds `capacity` values or
+///   `ttl_sec` seconds after its first value arrived, with the context of its first value.
+#[askit_agent(
+    title = "Window",
+    category = CATEGORY,
+    inputs = [PIN_VALUE],
+    outputs = [PIN_ARRAY],
+    integer_config(name = CONFIG_CAPACITY, default = 10),
+    integer_config(name = CONFIG_TTL_SEC, default = 0),
+    integer_config(name = CONFIG_STEP, default = 1),
+    boolean_config(name = CONFIG_TUMBLING),
+)]
+struct WindowAgent {
+    data: AgentData,
+    capacity: usize,
+    ttl_sec: u64,
+    step: usize,
+    tumbling: bool,
+
+    // Window contents, shared with the timer task in tumbling mode
+    state: Arc<Mutex<WindowState>>,
+}
+
+#[derive(Default)]
+struct WindowState {
+    // Context of the first value in a tumbling window
+    ctx: Option<AgentContext>,
+    values: VecDeque<(Instant, AgentValue)>,
+
+    // Values received since the last emission (sliding mode)
+    since_emit: usize,
+
+    // Incremented for every tumbling window so that a stale timer never closes a newer one
+    seq: u64,
+}
+
+impl WindowState {
+    fn to_array(&self) -> AgentValue {
+        AgentValue::array(self.values.iter().map(|(_, v)| v.clone()).collect())
+    }
+
+    fn take(&mut self) -> Option<(AgentContext, AgentValue)> {
+        let ctx = self.ctx.take()?;
+        let arr = self.to_array();
+        self.values.clear();
+        Some((ctx, arr))
+    }
+}
+
+impl WindowAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<(usize, u64, usize, bool), AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let capacity = cfg.get_integer_or(CONFIG_CAPACITY, 10).max(0) as usize;
+        let ttl_sec = cfg.get_integer_or(CONFIG_TTL_SEC, 0).max(0) as u64;
+        let step = cfg.get_integer_or(CONFIG_STEP, 1).max(1) as usize;
+        let tumbling = cfg.get_bool_or_default(CONFIG_TUMBLING);
+
+        Ok((capacity, ttl_sec, step, tumbling))
+    }
+
+    fn reset_state(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.ctx = None;
+        state.values.clear();
+        state.since_emit = 0;
+        state.seq += 1;
+    }
+
+    // Close the tumbling window with seq after ttl_sec unless it has been closed in the meantime
+    fn start_timer(&self, seq: u64) {
+        let state = self.state.clone();
+        let ttl_sec = self.ttl_sec;
+        let askit = self.askit().clone();
+        let agent_id = self.id().to_string();
+
+        self.runtime().spawn(async move {
+            tokio::time::sleep(Duration::from_secs(ttl_sec)).await;
+
+            let window = {
+                let mut state = state.lock().unwrap();
+                if state.seq != seq {
+                    return;
+                }
+                state.take()
+            };
+
+            if let Some((ctx, arr)) = window
+                && let Err(e) = askit.try_send_agent_out(agent_id, ctx, PIN_ARRAY.to_string(), arr)
+            {
+                log::error!("Failed to send window: {}", e);
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl AsAgent for WindowAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (capacity, ttl_sec, step, tumbling) = Self::update_spec(&mut spec)?;
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self {
+            data,
+            capacity,
+            ttl_sec,
+            step,
+            tumbling,
+            state: Default::default(),
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        let (capacity, ttl_sec, step, tumbling) = Self::update_spec(&mut self.data.spec)?;
+        let changed = (capacity, ttl_sec, step, tumbling)
+            != (self.capacity, self.ttl_sec, self.step, self.tumbling);
+        if changed {
+            self.capacity = capacity;
+            self.ttl_sec = ttl_sec;
+            self.step = step;
+            self.tumbling = tumbling;
+            self.reset_state();
+        }
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), AgentError> {
+        self.reset_state();
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let now = Instant::now();
+
+        let window = {
+            let mut state = self.state.lock().unwrap();
+
+            if self.tumbling {
+                if state.ctx.is_none() {
+                    // First value of a new window
+                    state.ctx = Some(ctx);
+                    state.seq += 1;
+                    if self.ttl_sec > 0 {
+                        self.start_timer(state.seq);
+                    }
+                }
+                state.values.push_back((now, value));
+                if self.capacity > 0 && state.values.len() >= self.capacity {
+                    state.take()
+                } else {
+                    None
+                }
+            } else {
+                state.values.push_back((now, value));
+                if self.ttl_sec > 0 {
+                    let ttl = Duration::from_secs(self.ttl_sec);
+                    while state
+                        .values
+                        .front()
+                        .is_some_and(|(t, _)| now.duration_since(*t) > ttl)
+                    {
+                        state.values.pop_front();
+                    }
+                }
+                if self.capacity > 0 {
+                    while state.values.len() > self.capacity {
+                        state.values.pop_front();
+                    }
+                }
+                state.since_emit += 1;
+                if state.since_emit >= self.step {
+                    state.since_emit = 0;
+                    Some((ctx, state.to_array()))
+                } else {
+                    None
+                }
+            }
+        };
+
+        if let Some((ctx, arr)) = window {
+            self.output(ctx, PIN_ARRAY, arr).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Maps over an input array, emitting each item individually with a `map` frame that captures the index and length.
+/// Nested maps accumulate frames to preserve lineage. If the input is not an array, it is treated as a single-item array.
+/// Each emitted item is reported on `progress` as `{i, n}`.
+///
+/// When `max_in_flight` is positive, emission waits for acknowledgements on `ack`, and queued items are emitted in order.
+/// `limit_by` decides what the limit counts:
+/// - `item`: at most `max_in_flight` items are emitted but not acknowledged yet;
+/// - `fan_out`: at most `max_in_flight` input arrays are being processed, and all items of an array are emitted
+///   together. Use this when the acknowledgement is the output of Collect, which only arrives after all items.
+///
+/// Valid acknowledgements are:
+/// - a value carrying the context of a whole fan-out (such as the output of Collect), which releases the slots
+///   still held by that fan-out;
+/// - a value carrying the `map` frame of an emitted item (such as the result of processing it), which releases
+///   the slot of that item. A fan-out releases its slot under `fan_out` once all of its items are acknowledged.
+///
+/// Other values on `ack` are ignored.
+#[askit_agent(
+    title = "Map",
+    category = CATEGORY,
+    inputs = [PIN_ARRAY, PIN_ACK],
+    outputs = [PIN_VALUE, PIN_PROGRESS],
+    integer_config(name = CONFIG_MAX_IN_FLIGHT, default = 0, description = "0: unlimited"),
+    string_config(name = CONFIG_LIMIT_BY, default = "item", description = "item or fan_out"),
+)]
+struct MapAgent {
+    data: AgentData,
+    max_in_flight: usize,
+    limit_by: LimitBy,
+
+    // Fan-out Context Key -> indices of the emitted items not acknowledged yet
+    in_flight: HashMap<String, Vec<usize>>,
+
+    // Items waiting for a free slot: (parent context, index, length, item)
+    pending: VecDeque<(AgentContext, usize, usize, AgentValue)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LimitBy {
+    Item,
+    FanOut,
+}
+
+impl MapAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<(usize, LimitBy), AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Ok((0, LimitBy::Item));
+        };
+        let max_in_flight = cfg.get_integer_or(CONFIG_MAX_IN_FLIGHT, 0).max(0) as usize;
+        let limit_by = match cfg.get_string_or(CONFIG_LIMIT_BY, "item").as_str() {
+            "item" => LimitBy::Item,
+            "fan_out" => LimitBy::FanOut,
+            other => {
+                return Err(AgentError::InvalidConfig(format!(
+                    "limit_by must be item or fan_out: {}",
+                    other
+                )));
+            }
+        };
+        Ok((max_in_flight, limit_by))
+    }
+
+    // Whether the next item of the fan-out with the given key can be emitted now
+    fn has_slot(&self, key: &str) -> bool {
+        if self.max_in_flight == 0 {
+            return true;
+        }
+        match self.limit_by {
+            LimitBy::Item => {
+                let count: usize = self.in_flight.values().map(|indices| indices.len()).sum();
+                count < self.max_in_flight
+            }
+            LimitBy::FanOut => {
+                self.in_flight.contains_key(key) || self.in_flight.len() < self.max_in_flight
+            }
+        }
+    }
+
+    // Release all slots of an acknowledged fan-out, or the slot of an acknowledged item
+    fn release(&mut self, ctx: &AgentContext) -> Result<(), AgentError> {
+        // Checked first: under nested maps, a fan-out ack still carries the frames of the outer maps
+        if self.in_flight.remove(&ctx.ctx_key()?).is_some() {
+            return Ok(());
+        }
+        let Some((i, _)) = ctx.current_map_frame()? else {
+            return Ok(());
+        };
+        let key = ctx.pop_map_frame()?.ctx_key()?;
+        if let Some(indices) = self.in_flight.get_mut(&key) {
+            if let Some(pos) = indices.iter().position(|&j| j == i) {
+                indices.swap_remove(pos);
+            }
+            if indices.is_empty() {
+                self.in_flight.remove(&key);
+            }
+        }
+        Ok(())
+    }
+
+    async fn emit_pending(&mut self) -> Result<(), AgentError> {
+        while let Some((ctx, _, _, _)) = self.pending.front() {
+            let key = ctx.ctx_key()?;
+            if !self.has_slot(&key) {
+                break;
+            }
+            let Some((ctx, i, n, item)) = self.pending.pop_front() else {
+                break;
+            };
+            if self.max_in_flight > 0 {
+                self.in_flight.entry(key).or_default().push(i);
+            }
+            let c = ctx.push_map_frame(i, n)?;
+            self.output(c, PIN_VALUE, item).await?;
+
+            let mut progress = AgentValue::object_default();
+            progress.set("i".to_string(), AgentValue::integer(i as i64))?;
+            progress.set("n".to_string(), AgentValue::integer(n as i64))?;
+            self.output(ctx, PIN_PROGRESS, progress).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AsAgent for MapAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (max_in_flight, limit_by) = Self::update_spec(&mut spec)?;
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self {
+            data,
+            max_in_flight,
+            limit_by,
+            in_flight: HashMap::new(),
+            pending: VecDeque::new(),
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        (self.max_in_flight, self.limit_by) = Self::update_spec(&mut self.data.spec)?;
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), AgentError> {
+        self.pending.clear();
+        self.in_flight.clear();
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        if pin == PIN_ACK {
+            self.release(&ctx)?;
+            return self.emit_pending().await;
+        }
+
+        match value {
+            AgentValue::Array(arr) => {
+                let n = arr.len();
+                for (i, item) in arr.into_iter().enumerate() {
+                    self.pending.push_back((ctx.clone(), i, n, item));
+                }
+            }
+            other => {
+                self.pending.push_back((ctx, 0, 1, other));
+            }
+        }
+        self.emit_pending().await
+    }
+}
+
+/// Collects input values into an array.
+///
+/// Expects a `map` frame to determine the position and length for each input value.
+/// The `map` frame stores keys `i` (index) and `n` (length). Nested maps stack frames.
+/// If a `map` frame is not present, the input value is emitted directly.
+///
+/// Each fan-out (the context key with the `map` frame popped) is gathered in its own buffer, so
+/// interleaved fan-outs are collected independently.
+///
+/// An incomplete collection is finished when `timeout` elapses after its first item arrived.
+/// The `partial` policy decides what is emitted on `array` in that case: `drop` emits nothing,
+/// `unit` fills the missing positions with Unit and `compact` emits only the received values in order.
+/// The missing indices are always reported on `missing`, and items arriving later for the same fan-out
+/// are dropped. Without `timeout`, or when it is longer, `ttl_sec` is used instead, so an incomplete
+/// collection is never discarded without reporting on `missing`. When a new collection would exceed `capacity`,
+/// the oldest pending collection is finished right away as if it had timed out.
+#[askit_agent(
+    title = "Collect",
+    category = CATEGORY,
+    description = "Collects input values into an array",
+    inputs = [PIN_VALUE],
+    outputs = [PIN_ARRAY, PIN_MISSING],
+    string_config(name = CONFIG_TIMEOUT, description = "(ex. 10s, 500ms) empty: no timeout"),
+    string_config(name = CONFIG_PARTIAL, default = "drop", description = "drop, unit or compact"),
+    integer_config(name = CONFIG_TTL_SEC, default = 60),
+    integer_config(name = CONFIG_CAPACITY, default = 1000),
+)]
+struct CollectAgent {
+    data: AgentData,
+    timeout_ms: Option<u64>,
+    partial: PartialPolicy,
+    ttl_sec: u64,
+    capacity: u64,
+
+    // Parent Context Key -> pending collection, shared with the timeout tasks
+    ctx_buffers: CollectBuffers,
+
+    // Parent Context Keys of the collections finished before all items arrived
+    expired: Cache<String, ()>,
+}
+
+// Pending collections with their start time. Entries are removed when they finish, so the size
+// stays within the capacity.
+type CollectBuffers = Arc<Mutex<BTreeMap<String, (Instant, Arc<Mutex<PendingCollect>>)>>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PartialPolicy {
+    Drop,
+    Unit,
+    Compact,
+}
+
+struct PendingCollect {
+    // Context used for the output (map frame popped)
+    out_ctx: AgentContext,
+
+    // Data buffer
+    values: Vec<Option<AgentValue>>,
+
+    // Number of items received (counter to avoid scanning values every time)
+    received_count: usize,
+
+    // Set once the collection has been emitted or discarded, so that it is never finished twice
+    finished: bool,
+
+    timer: Option<JoinHandle<()>>,
+}
+
+impl PendingCollect {
+    fn new(out_ctx: AgentContext, n: usize) -> Self {
+        Self {
+            out_ctx,
+            // Fill with None for the required size
+            values: vec![None; n],
+            received_count: 0,
+            finished: false,
+            timer: None,
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.received_count == self.values.len()
+    }
+
+    // Mark the collection as finished and stop its timer
+    fn finish(&mut self) {
+        self.finished = true;
+        if let Some(timer) = self.timer.take() {
+            timer.abort();
+        }
+    }
+
+    // Take the buffer as im::Vector, filling missing values with Unit
+    fn take_vector(&mut self) -> Vector<AgentValue> {
+        std::mem::take(&mut self.values)
+            .into_iter()
+            .map(|v| v.unwrap_or(AgentValue::Unit))
+            .collect()
+    }
+
+    // Take the partial array (according to the policy) and the array of missing indices
+    fn take_partial(&mut self, policy: PartialPolicy) -> (Option<AgentValue>, AgentValue) {
+        let missing: Vector<AgentValue> = self
+            .values
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.is_none())
+            .map(|(i, _)| AgentValue::integer(i as i64))
+            .collect();
+        let arr = match policy {
+            PartialPolicy::Drop => None,
+            PartialPolicy::Unit => Some(AgentValue::array(self.take_vector())),
+            PartialPolicy::Compact => Some(AgentValue::array(
+                std::mem::take(&mut self.values)
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+            )),
+        };
+        (arr, AgentValue::array(missing))
+    }
+}
+
+impl CollectAgent {
+    fn update_spec(
+        spec: &mut AgentSpec,
+    ) -> Result<(Option<u64>, PartialPolicy, u64, u64), AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let timeout = cfg.get_string_or_default(CONFIG_TIMEOUT);
+        let timeout_ms = if timeout.trim().is_empty() {
+            None
+        } else {
+            Some(parse_duration_to_ms(&timeout)?)
+        };
+
+        let partial = match cfg.get_string_or(CONFIG_PARTIAL, "drop").as_str() {
+            "drop" => PartialPolicy::Drop,
+            "unit" => PartialPolicy::Unit,
+            "compact" => PartialPolicy::Compact,
+            other => {
+                return Err(AgentError::InvalidConfig(format!(
+                    "partial must be drop, unit or compact: {}",
+                    other
+                )));
+            }
+        };
+
+        let ttl_sec = cfg.get_integer_or(CONFIG_TTL_SEC, 60) as u64;
+        let capacity = cfg.get_integer_or(CONFIG_CAPACITY, 1000) as u64;
+
+        Ok((timeout_ms, partial, ttl_sec, capacity))
+    }
+
+    fn build_cache(ttl_sec: u64, capacity: u64) -> Cache<String, ()> {
+        Cache::builder()
+            .max_capacity(capacity)
+            .time_to_live(Duration::from_secs(ttl_sec))
+            .build()
+    }
+
+    fn reset_state(&mut self) {
+        let pending = std::mem::take(&mut *self.ctx_buffers.lock().unwrap());
+        // Stop the pending timers so that discarded collections are never emitted
+        for (_, entry) in pending.into_values() {
+            entry.lock().unwrap().finish();
+        }
+        self.expired.invalidate_all();
+    }
+
+    // Finish the oldest pending collection early if a new one would exceed the capacity
+    async fn evict_oldest(&mut self) -> Result<(), AgentError> {
+        let oldest = {
+            let buffers = self.ctx_buffers.lock().unwrap();
+            if (buffers.len() as u64) < self.capacity {
+                return Ok(());
+            }
+            buffers
+                .iter()
+                .min_by_key(|(_, (started, _))| *started)
+                .map(|(key, (_, entry))| (key.clone(), entry.clone()))
+        };
+        let Some((ctx_key, entry)) = oldest else {
+            return Ok(());
+        };
+        let Some((out_ctx, arr, missing)) = expire_collect(
+            &self.ctx_buffers,
+            &self.expired,
+            ctx_key,
+            &entry,
+            self.partial,
+        ) else {
+            return Ok(());
+        };
+        if let Some(timer) = entry.lock().unwrap().timer.take() {
+            timer.abort();
+        }
+
+        log::warn!("Collection evicted before all items arrived.");
+        if let Some(arr) = arr {
+            self.output(out_ctx.clone(), PIN_ARRAY, arr).await?;
+        }
+        self.output(out_ctx, PIN_MISSING, missing).await
+    }
+
+    // Finish the collection after the timeout unless it has completed in the meantime
+    fn start_timer(
+        &self,
+        ctx_key: String,
+        pending: Arc<Mutex<PendingCollect>>,
+        timeout_ms: u64,
+    ) -> JoinHandle<()> {
+        let ctx_buffers = self.ctx_buffers.clone();
+        let expired = self.expired.clone();
+        let policy = self.partial;
+        let askit = self.askit().clone();
+        let agent_id = self.id().to_string();
+
+        self.runtime().spawn(async move {
+            tokio::time::sleep(Duration::from_millis(timeout_ms)).await;
+
+            let Some((out_ctx, arr, missing)) =
+                expire_collect(&ctx_buffers, &expired, ctx_key, &pending, policy)
+            else {
+                return;
+            };
+
+            log::warn!("Collection timed out before all items arrived.");
+            if let Some(arr) = arr
+                && let Err(e) = askit.try_send_agent_out(
+                    agent_id.clone(),
+                    out_ctx.clone(),
+                    PIN_ARRAY.to_string(),
+                    arr,
+                )
+            {
+                log::error!("Failed to send partial collection: {}", e);
+            }
+            if let Err(e) =
+                askit.try_send_agent_out(agent_id, out_ctx, PIN_MISSING.to_string(), missing)
+            {
+                log::error!("Failed to send missing indices: {}", e);
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl AsAgent for CollectAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (timeout_ms, partial, ttl_sec, capacity) = Self::update_spec(&mut spec)?;
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self {
+            data,
+            timeout_ms,
+            partial,
+            ttl_sec,
+            capacity,
+            ctx_buffers: CollectBuffers::default(),
+            expired: Self::build_cache(ttl_sec, capacity),
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        let (timeout_ms, partial, ttl_sec, capacity) = Self::update_spec(&mut self.data.spec)?;
+        self.timeout_ms = timeout_ms;
+        self.partial = partial;
+        if ttl_sec != self.ttl_sec || capacity != self.capacity {
+            self.ttl_sec = ttl_sec;
+            self.capacity = capacity;
+            self.reset_state();
+            // Rebuild the cache with new capacity and TTL
+            self.expired = Self::build_cache(ttl_sec, capacity);
+        }
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), AgentError> {
+        self.reset_state();
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        // Check for map frame
+        // If not within a map, pass the value through as-is.
+        let Some((idx, n)) = ctx.current_map_frame()? else {
+            return self.output(ctx, PIN_ARRAY, value).await;
+        };
+
+        let out_ctx = ctx.pop_map_frame()?;
+        let ctx_key = out_ctx.ctx_key()?;
+
+        if self.expired.contains_key(&ctx_key) {
+            log::warn!(
+                "Dropping an item that arrived after its collection timed out or was evicted."
+            );
+            return Ok(());
+        }
+
+        // Get the pending collection, or initialize it when the first item of this fan-out arrives
+        let existing = self
+            .ctx_buffers
+            .lock()
+            .unwrap()
+            .get(&ctx_key)
+            .map(|(_, entry)| entry.clone());
+        let entry = match existing {
+            Some(entry) => entry,
+            None => {
+                self.evict_oldest().await?;
+                let entry = Arc::new(Mutex::new(PendingCollect::new(out_ctx, n)));
+                let ttl_ms = self.ttl_sec * 1000;
+                let timeout_ms = self.timeout_ms.map_or(ttl_ms, |t| t.min(ttl_ms));
+                let timer = self.start_timer(ctx_key.clone(), entry.clone(), timeout_ms);
+                entry.lock().unwrap().timer = Some(timer);
+                self.ctx_buffers
+                    .lock()
+                    .unwrap()
+                    .insert(ctx_key.clone(), (Instant::now(), entry.clone()));
+                entry
+            }
+        };
+
+        let completed = {
+            let mut pending = entry.lock().unwrap();
+
+            if pending.finished {
+                // The timeout fired while this item was on its way
+                log::warn!("Dropping an item that arrived after its collection timed out.");
+                return Ok(());
+            }
+
+            // Validation
+            if n != pending.values.len() {
+                // Size shouldn't change within the same fan-out, but check just in case
+                return Err(AgentError::InvalidValue(
+                    "Map frame size mismatch within the same context".into(),
+                ));
+            }
+            if idx >= n {
+                return Err(AgentError::InvalidValue(
+                    "Map frame index is out of bounds".into(),
+                ));
+            }
+
+            // Store data
+            // If duplicate data arrives, overwrite (could also error instead).
+            if pending.values[idx].is_none() {
+                pending.received_count += 1;
+            }
+            pending.values[idx] = Some(value);
+
+            // Check for completion
+            if pending.is_complete() {
+                pending.finish();
+                self.ctx_buffers.lock().unwrap().remove(&ctx_key);
+                Some((pending.out_ctx.clone(), pending.take_vector()))
+            } else {
+                None
+            }
+        };
+
+        // Not yet complete, keep waiting
+        let Some((next_ctx, arr)) = completed else {
+            return Ok(());
+        };
+
+        // All items collected, output the result with one map frame popped
+        self.output(next_ctx, PIN_ARRAY, AgentValue::array(arr))
+            .await
+    }
+}
+
+// Finish an incomplete collection and leave a tombstone so that its later items are dropped.
+// Returns the output context, the partial array and the missing indices, or None if it has already finished.
+fn expire_collect(
+    ctx_buffers: &CollectBuffers,
+    expired: &Cache<String, ()>,
+    ctx_key: String,
+    pending: &Mutex<PendingCollect>,
+    policy: PartialPolicy,
+) -> Option<(AgentContext, Option<AgentValue>, AgentValue)> {
+    let mut pending = pending.lock().unwrap();
+    if pending.finished {
+        return None;
+    }
+    pending.finished = true;
+    ctx_buffers.lock().unwrap().remove(&ctx_key);
+    expired.insert(ctx_key, ());
+    let (arr, missing) = pending.take_partial(policy);
+    Some((pending.out_ctx.clone(), arr, missing))
+}
+
+/// Folds mapped values into a single accumulated value.
+///
+/// Expects a `map` frame like Collect. Items belonging to the same fan-out (the context with the `map`
+/// frame popped) share an accumulator, and are folded in index order as soon as all preceding items
+/// have arrived. Once all `n` items are folded, the result is emitted with the `map` frame popped.
+/// If a `map` frame is not present, an array is reduced over its items (any other value on its own)
+/// and the result is emitted directly.
+///
+/// An item the reducer cannot fold (such as a string for `sum`) is skipped and reported as an error,
+/// while the rest of the fan-out is still folded and emitted.
+///
+/// Reducers: `sum`, `min`, `max`, `count`, `concat` (strings or arrays), `merge` (objects, later wins),
+/// `first`, `last`. For `min` and `max`, `key` selects the key path to compare so that the whole item
+/// with the smallest or largest key is emitted.
+/// Nothing folded gives `0` for `sum` and `count`, `[]` for `concat`, `{}` for `merge` and null otherwise.
+#[askit_agent(
+    title = "Reduce",
+    category = CATEGORY,
+    inputs = [PIN_VALUE],
+    outputs = [PIN_VALUE],
+    string_config(name = CONFIG_REDUCER, default = "sum", description = "sum, min, max, count, concat, merge, first or last"),
+    string_config(name = CONFIG_KEY),
+    integer_config(name = CONFIG_TTL_SEC, default = 60),
+    integer_config(name = CONFIG_CAPACITY, default = 1000),
+)]
+struct ReduceAgent {
+    data: AgentData,
+    reducer: Reducer,
+    target_keys: Vec<String>,
+    ttl_sec: u64,
+    capacity: u64,
+
+    // Parent Context Key -> PendingReduce
+    ctx_buffers: Cache<String, PendingReduce>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Reducer {
+    Sum,
+    Min,
+    Max,
+    Count,
+    Concat,
+    Merge,
+    First,
+    Last,
+}
+
+#[derive(Clone, Default)]
+struct PendingReduce {
+    acc: Option<AgentValue>,
+    // Index of the next item to fold
+    next: usize,
+    // Items that arrived ahead of `next`
+    held: BTreeMap<usize, AgentValue>,
+}
+
+impl Reducer {
+    /// Returns the result of folding no items.
+    fn identity(self) -> AgentValue {
+        match self {
+            Reducer::Sum | Reducer::Count => AgentValue::integer(0),
+            Reducer::Concat => AgentValue::array_default(),
+            Reducer::Merge => AgentValue::object_default(),
+            Reducer::Min | Reducer::Max | Reducer::First | Reducer::Last => AgentValue::Unit,
+        }
+    }
+
+    /// Folds all items in order, starting from the identity.
+    fn reduce(
+        self,
+        target_keys: &[String],
+        items: impl IntoIterator<Item = AgentValue>,
+    ) -> Result<AgentValue, AgentError> {
+        let mut acc = None;
+        for item in items {
+            acc = Some(self.fold(target_keys, acc, item)?);
+        }
+        Ok(acc.unwrap_or_else(|| self.identity()))
+    }
+
+    fn fold(
+        self,
+        target_keys: &[String],
+        acc: Option<AgentValue>,
+        item: AgentValue,
+    ) -> Result<AgentValue, AgentError> {
+        let Some(acc) = acc else {
+            return Ok(match self {
+                Reducer::Count => AgentValue::integer(1),
+                Reducer::Concat if !item.is_string() && !item.is_array() => {
+                    AgentValue::array(vector![item])
+                }
+                _ => item,
+            });
+        };
+        match self {
+            Reducer::Sum => match (&acc, &item) {
+                (AgentValue::Integer(a), AgentValue::Integer(b)) if a.checked_add(*b).is_some() => {
+                    Ok(AgentValue::integer(a + b))
+                }
+                _ => match (acc.as_f64(), item.as_f64()) {
+                    (Some(a), Some(b)) => Ok(AgentValue::number(a + b)),
+                    _ => Err(AgentError::InvalidValue(
+                        "sum requires numeric values".into(),
+                    )),
+                },
+            },
+            Reducer::Min | Reducer::Max => {
+                let key_of = |v: &AgentValue| {
+                    get_nested_value(v, target_keys).and_then(|k| sort_key(k, CompareMode::Auto))
+                };
+                let replace = match (key_of(&acc), key_of(&item)) {
+                    (_, None) => false,
+                    (None, Some(_)) => true,
+                    (Some(a), Some(b)) => {
+                        let ord = compare_sort_keys(&b, &a, CompareMode::Auto);
+                        if self == Reducer::Min {
+                            ord.is_lt()
+                        } else {
+                            ord.is_gt()
+                        }
+                    }
+                };
+                Ok(if replace { item } else { acc })
+            }
+            Reducer::Count => Ok(AgentValue::integer(acc.as_i64().unwrap_or(0) + 1)),
+            Reducer::Concat => match (acc, item) {
+                (AgentValue::String(a), b) => {
+                    let b = b
+                        .to_string()
+                        .or_else(|| serde_json::to_string(&b).ok())
+                        .unwrap_or_default();
+                    Ok(AgentValue::string(format!("{}{}", a, b)))
+                }
+                (AgentValue::Array(mut a), AgentValue::Array(b)) => {
+                    a.append(b);
+                    Ok(AgentValue::array(a))
+                }
+                (AgentValue::Array(mut a), b) => {
+                    a.push_back(b);
+                    Ok(AgentValue::array(a))
+                }
+                _ => Err(AgentError::InvalidValue(
+                    "concat requires strings or arrays".into(),
+                )),
+            },
+            Reducer::Merge => match (acc, item) {
+                (AgentValue::Object(a), AgentValue::Object(b)) => {
+                    Ok(AgentValue::object(b.union(a)))
+                }
+                _ => Err(AgentError::InvalidValue("merge requires objects".into())),
+            },
+            Reducer::First => Ok(acc),
+            Reducer::Last => Ok(item),
+        }
+    }
+}
+
+impl PendingReduce {
+    // Hold the item and fold all items that are now contiguous. An item that fails to fold is
+    // skipped, and the first error is returned once the following items are folded.
+    fn push(
+        &mut self,
+        idx: usize,
+        value: AgentValue,
+        reducer: Reducer,
+        target_keys: &[String],
+    ) -> Result<(), AgentError> {
+        if idx < self.next {
+            // Duplicate of an already folded item
+            return Ok(());
+        }
+        self.held.insert(idx, value);
+
+        let mut error = None;
+        while let Some(item) = self.held.remove(&self.next) {
+            self.next += 1;
+            match reducer.fold(target_keys, self.acc.clone(), item) {
+                Ok(acc) => self.acc = Some(acc),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        error.map_or(Ok(()), Err)
+    }
+}
+
+impl ReduceAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<(Reducer, Vec<String>, u64, u64), AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let reducer = match cfg.get_string_or(CONFIG_REDUCER, "sum").as_str() {
+            "sum" => Reducer::Sum,
+            "min" => Reducer::Min,
+            "max" => Reducer::Max,
+            "count" => Reducer::Count,
+            "concat" => Reducer::Concat,
+            "merge" => Reducer::Merge,
+            "first" => Reducer::First,
+            "last" => Reducer::Last,
+            other => {
+                return Err(AgentError::InvalidConfig(format!(
+                    "Unknown reducer: {}",
+                    other
+                )));
+            }
+        };
+
+        let key_str = cfg.get_string_or_default(CONFIG_KEY);
+        let target_keys = parse_key_path(&key_str);
+
+        let ttl_sec = cfg.get_integer_or(CONFIG_TTL_SEC, 60) as u64;
+        let capacity = cfg.get_integer_or(CONFIG_CAPACITY, 1000) as u64;
+
+        Ok((reducer, target_keys, ttl_sec, capacity))
+    }
+}
+
+#[async_trait]
+impl AsAgent for ReduceAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (reducer, target_keys, ttl_sec, capacity) = Self::update_spec(&mut spec)?;
+
+        let cache = Cache::builder()
+            .max_capacity(capacity)
+            .time_to_live(Duration::from_secs(ttl_sec))
+            .build();
+
+        let data = AgentData::new(askit, id, spec);
+
+        Ok(Self {
+            data,
+            reducer,
+            target_keys,
+            ttl_sec,
+            capacity,
+            ctx_buffers: cache,
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        let (reducer, target_keys, ttl_sec, capacity) = Self::update_spec(&mut self.data.spec)?;
+        if reducer != self.reducer || target_keys != self.target_keys {
+            self.reducer = reducer;
+            self.target_keys = target_keys;
+            self.ctx_buffers.invalidate_all();
+        }
+        if ttl_sec != self.ttl_sec || capacity != self.capacity {
+            self.ttl_sec = ttl_sec;
+            self.capacity = capacity;
+            // Rebuild cache with new capacity and TTL
+            self.ctx_buffers = Cache::builder()
+                .max_capacity(capacity)
+                .time_to_live(Duration::from_secs(ttl_sec))
+                .build();
+        }
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), AgentError> {
+        self.ctx_buffers.invalidate_all();
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let Some((idx, n)) = ctx.current_map_frame()? else {
+            let items = match value {
+                AgentValue::Array(arr) => arr,
+                other => vector![other],
+            };
+            let result = self.reducer.reduce(&self.target_keys, items)?;
+            return self.output(ctx, PIN_VALUE, result).await;
+        };
+
+        let next_ctx = ctx.pop_map_frame()?;
+        let ctx_key = next_ctx.ctx_key()?;
+
+        // The state is written back even if an item failed to fold, so the fan-out still completes
+        let mut entry = self.ctx_buffers.get(&ctx_key).unwrap_or_default();
+        let folded = entry.push(idx, value, self.reducer, &self.target_keys);
+
+        if entry.next == n {
+            self.ctx_buffers.invalidate(&ctx_key);
+            let result = entry.acc.unwrap_or_else(|| self.reducer.identity());
+            self.output(next_ctx, PIN_VALUE, result).await?;
+        } else {
+            self.ctx_buffers.insert(ctx_key, entry);
+        }
+        folded
+    }
+}
+
+/// Zips multiple inputs into an array.
+///
+/// The number of inputs n is specified via configuration.
+///
+/// If n=2, it takes two inputs: in1 and in2. Once all inputs are present,
+/// it emits them as [in1, in2].
+///
+/// If in2 arrives repeatedly before in1, the in2 values are queued; when in1 arrives,
+/// they’re paired in order from the head of the queue and emitted.
+///
+/// When the `use_ctx` config is true, inputs are matched by context key (including map frames)
+/// so that mapped items zip correctly even when they interleave.
+#[askit_agent(
+    title = "ZipToArray",
+    category = CATEGORY,
+    inputs = [PIN_IN1, PIN_IN2],
+    outputs = [PIN_ARRAY],
+    integer_config(name = CONFIG_N, default = 2),
+    boolean_config(name = CONFIG_USE_CTX),
+    integer_config(name = CONFIG_TTL_SEC, default = 60), 
+    integer_config(name = CONFIG_CAPACITY, default = 1000),
+)]
+struct ZipToArrayAgent {
+    data: AgentData,
+    n: usize,
+    use_ctx: bool,
+
+    ttl_sec: u64,
+    capacity: u64,
+    zip: ZipBuffer,
+}
+
+impl ZipToArrayAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<(usize, bool, u64, u64), AgentError> {
+        let mut n = spec
+            .configs
+            .as_ref()
+            .map(|cfg| cfg.get_integer_or(CONFIG_N, 2))
+            .unwrap_or(2) as usize;
+        if n < 1 {
+            n = 1;
+        }
+
+        let (use_ctx, ttl_sec, capacity) = zip_configs(spec);
+
+        spec.inputs = Some((1..=n).map(|i| format!("in{}", i)).collect());
+
+        Ok((n, use_ctx, ttl_sec, capacity))
+    }
+}
+
+#[async_trait]
+impl AsAgent for ZipToArrayAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (n, use_ctx, ttl_sec, capacity) = Self::update_spec(&mut spec)?;
+
+        let data = AgentData::new(askit, id, spec);
+
+        Ok(Self {
+            data,
+            n,
+            use_ctx,
+            ttl_sec,
+            capacity,
+            zip: ZipBuffer::new(n, use_ctx, ttl_sec, capacity),
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        let (n, use_ctx, ttl_sec, capacity) = Self::update_spec(&mut self.data.spec)?;
+        let mut changed = false;
+        if n != self.n {
+            self.n = n;
+            changed = true;
+        }
+        if use_ctx != self.use_ctx {
+            self.use_ctx = use_ctx;
+            changed = true;
+        }
+        if ttl_sec != self.ttl_sec {
+            self.ttl_sec = ttl_sec;
+            changed = true;
+        }
+        if capacity != self.capacity {
+            self.capacity = capacity;
+            changed = true;
+        }
+        if changed {
+            // Rebuild buffers with new capacity and TTL
+            self.zip = ZipBuffer::new(n, use_ctx, ttl_sec, capacity);
+            self.emit_agent_spec_updated();
+        }
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), AgentError> {
+        self.zip.reset();
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        if let Some(arr) = self.zip.push(&ctx, &pin, value)? {
+            return self.output(ctx, PIN_ARRAY, AgentValue::array(arr)).await;
+        }
+        Ok(())
+    }
+}
+
+/// Pairs values arriving on inputs in1..inN.
+///
+/// Without `use_ctx`, values are queued per pin and matched in arrival order.
+/// With `use_ctx`, values are matched by context key (including map frames); unmatched entries
+/// expire after `ttl_sec` seconds and at most `capacity` contexts are buffered.
+pub(crate) struct ZipBuffer {
+    n: usize,
+    use_ctx: bool,
+    queues: Vec<VecDeque<AgentValue>>, // for non-ctx mode
+
+    // Context Key -> PendingZip
+    ctx_buffers: Cache<String, PendingZip>,
+}
+
+#[derive(Clone)]
+struct PendingZip {
+    values: Vec<Option<AgentValue>>,
+    count: usize,
+}
+
+impl ZipBuffer {
+    pub(crate) fn new(n: usize, use_ctx: bool, ttl_sec: u64, capacity: u64) -> Self {
+        let cache = Cache::builder()
+            .max_capacity(capacity) // Capacity limit (oldest entries are evicted on overflow)
+            .time_to_live(Duration::from_secs(ttl_sec)) // TTL (entries expire X seconds after write)
+            .build();
+
+        Self {
+            n,
+            use_ctx,
+            queues: vec![VecDeque::new(); n],
+            ctx_buffers: cache,
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.queues = vec![VecDeque::new(); self.n];
+        self.ctx_buffers.invalidate_all();
+    }
+
+    /// Buffers a value arriving on pin, returning the values of all pins in order once they are complete.
+    pub(crate) fn push(
+        &mut self,
+        ctx: &AgentContext,
+        pin: &str,
+        value: AgentValue,
+    ) -> Result<Option<Vector<AgentValue>>, AgentError> {
+        // Parse pin number
+        let Some(idx) = pin
+            .strip_prefix("in")
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&i| i >= 1 && i <= self.n)
+            .map(|i| i - 1)
+        else {
+            return Err(AgentError::InvalidValue(format!(
+                "Invalid input pin: {}",
+                pin
+            )));
+        };
+
+        if self.use_ctx {
+            let ctx_key = ctx.ctx_key()?;
+
+            // Get from cache (or create new if not present)
+            let mut entry = self
+                .ctx_buffers
+                .get(&ctx_key)
+                .unwrap_or_else(|| PendingZip {
+                    values: vec![None; self.n],
+                    count: 0,
+                });
+
+            // Update
+            if entry.values[idx].is_none() {
+                entry.count += 1;
+            }
+            entry.values[idx] = Some(value);
+
+            // Check for completion
+            if entry.count == self.n {
+                // All inputs collected, remove from cache (invalidate)
+                self.ctx_buffers.invalidate(&ctx_key);
+
+                let arr: Vector<AgentValue> =
+                    entry.values.into_iter().map(|v| v.unwrap()).collect();
+
+                return Ok(Some(arr));
+            }
+
+            self.ctx_buffers.insert(ctx_key, entry);
+            return Ok(None);
+        }
+
+        // Simple FIFO mode processing
+        self.queues[idx].push_back(value);
+
+        // Check if all queues have data
+        if self.queues.iter().all(|q| !q.is_empty()) {
+            let arr: Vector<AgentValue> = self
+                .queues
+                .iter_mut()
+                .map(|q| q.pop_front().unwrap())
+                .collect();
+
+            Ok(Some(arr))
         } else {
-            Ok(())
+            Ok(None)
+        }
+    }
+}
+
+/// Reads the `use_ctx`, `ttl_sec` and `capacity` configs of agents pairing inputs with ZipBuffer.
+pub(crate) fn zip_configs(spec: &AgentSpec) -> (bool, u64, u64) {
+    let use_ctx = spec
+        .configs
+        .as_ref()
+        .map(|cfg| cfg.get_bool_or_default(CONFIG_USE_CTX))
+        .unwrap_or(false);
+
+    let ttl_sec = spec
+        .configs
+        .as_ref()
+        .map(|c| c.get_integer_or(CONFIG_TTL_SEC, 60))
+        .unwrap_or(60) as u64;
+
+    let capacity = spec
+        .configs
+        .as_ref()
+        .map(|c| c.get_integer_or(CONFIG_CAPACITY, 1000))
+        .unwrap_or(1000) as u64;
+
+    (use_ctx, ttl_sec, capacity)
+}
+
+/// Combines the arrays on in1 and in2 with a set operation:
+///
+/// - `union`: every item of in1, then the items of in2 not in in1
+/// - `intersection`: the items of in1 that are also in in2
+/// - `difference`: the items of in1 that are not in in2
+/// - `symmetric_difference`: the items of in1 not in in2, then those of in2 not in in1
+///
+/// Items are compared by the value at a dotted key path, or as whole values if the key is empty.
+/// Duplicates are dropped, keeping the first-seen item. Non-array inputs are treated as single-item arrays.
+/// in1 and in2 are paired like ZipToArray, including the `use_ctx` matching.
+#[askit_agent(
+    title = "ArraySetOp",
+    category = CATEGORY,
+    inputs = [PIN_IN1, PIN_IN2],
+    outputs = [PIN_ARRAY],
+    string_config(name = CONFIG_OP, default = "union", description = "union, intersection, difference or symmetric_difference"),
+    string_config(name = CONFIG_KEY),
+    boolean_config(name = CONFIG_USE_CTX),
+    integer_config(name = CONFIG_TTL_SEC, default = 60),
+    integer_config(name = CONFIG_CAPACITY, default = 1000),
+)]
+struct ArraySetOpAgent {
+    data: AgentData,
+    op: SetOp,
+    target_keys: Vec<String>,
+    use_ctx: bool,
+    ttl_sec: u64,
+    capacity: u64,
+    zip: ZipBuffer,
+}
+
+impl ArraySetOpAgent {
+    fn update_spec(spec: &AgentSpec) -> Result<(SetOp, Vec<String>), AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let op = match cfg.get_string_or(CONFIG_OP, "union").as_str() {
+            "union" => SetOp::Union,
+            "intersection" => SetOp::Intersection,
+            "difference" => SetOp::Difference,
+            "symmetric_difference" => SetOp::SymmetricDifference,
+            other => {
+                return Err(AgentError::InvalidConfig(format!(
+                    "Unknown set operation: {}",
+                    other
+                )));
+            }
+        };
+
+        let key_str = cfg.get_string_or_default(CONFIG_KEY);
+        let target_keys = parse_key_path(&key_str);
+
+        Ok((op, target_keys))
+    }
+}
+
+#[async_trait]
+impl AsAgent for ArraySetOpAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        let (op, target_keys) = Self::update_spec(&spec)?;
+        let (use_ctx, ttl_sec, capacity) = zip_configs(&spec);
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self {
+            data,
+            op,
+            target_keys,
+            use_ctx,
+            ttl_sec,
+            capacity,
+            zip: ZipBuffer::new(2, use_ctx, ttl_sec, capacity),
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        (self.op, self.target_keys) = Self::update_spec(&self.data.spec)?;
+        let (use_ctx, ttl_sec, capacity) = zip_configs(&self.data.spec);
+        if (use_ctx, ttl_sec, capacity) != (self.use_ctx, self.ttl_sec, self.capacity) {
+            self.use_ctx = use_ctx;
+            self.ttl_sec = ttl_sec;
+            self.capacity = capacity;
+            self.zip = ZipBuffer::new(2, use_ctx, ttl_sec, capacity);
+        }
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), AgentError> {
+        self.zip.reset();
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let Some(mut pair) = self.zip.push(&ctx, &pin, value)? else {
+            return Ok(());
+        };
+        let to_vector = |value: AgentValue| match value {
+            AgentValue::Array(arr) => arr,
+            other => vector![other],
+        };
+        let b = to_vector(pair.pop_back().unwrap());
+        let a = to_vector(pair.pop_back().unwrap());
+        let result = set_op(self.op, a, b, &self.target_keys);
+        self.output(ctx, PIN_ARRAY, AgentValue::array(result)).await
+    }
+}
+
+/// Emits the Cartesian product of the arrays on n inputs (in1, in2, ...) as an array of arrays.
+///
+/// Each inner array takes one item from every input, in pin order; the last input varies fastest.
+/// Non-array inputs are treated as single-item arrays. Inputs are paired like ZipToArray,
+/// including the `use_ctx` matching. The number of inner arrays is capped like the length of ArrayRange.
+#[askit_agent(
+    title = "ArrayProduct",
+    category = CATEGORY,
+    inputs = [PIN_IN1, PIN_IN2],
+    outputs = [PIN_ARRAY],
+    integer_config(name = CONFIG_N, default = 2),
+    boolean_config(name = CONFIG_USE_CTX),
+    integer_config(name = CONFIG_TTL_SEC, default = 60),
+    integer_config(name = CONFIG_CAPACITY, default = 1000),
+)]
+struct ArrayProductAgent {
+    data: AgentData,
+    n: usize,
+    use_ctx: bool,
+    ttl_sec: u64,
+    capacity: u64,
+    zip: ZipBuffer,
+}
+
+impl ArrayProductAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<(usize, bool, u64, u64), AgentError> {
+        let n = spec
+            .configs
+            .as_ref()
+            .map(|cfg| cfg.get_integer_or(CONFIG_N, 2))
+            .unwrap_or(2)
+            .max(1) as usize;
+
+        let (use_ctx, ttl_sec, capacity) = zip_configs(spec);
+
+        spec.inputs = Some((1..=n).map(|i| format!("in{}", i)).collect());
+
+        Ok((n, use_ctx, ttl_sec, capacity))
+    }
+}
+
+#[async_trait]
+impl AsAgent for ArrayProductAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (n, use_ctx, ttl_sec, capacity) = Self::update_spec(&mut spec)?;
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self {
+            data,
+            n,
+            use_ctx,
+            ttl_sec,
+            capacity,
+            zip: ZipBuffer::new(n, use_ctx, ttl_sec, capacity),
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        let (n, use_ctx, ttl_sec, capacity) = Self::update_spec(&mut self.data.spec)?;
+        if (n, use_ctx, ttl_sec, capacity) != (self.n, self.use_ctx, self.ttl_sec, self.capacity) {
+            let n_changed = n != self.n;
+            self.n = n;
+            self.use_ctx = use_ctx;
+            self.ttl_sec = ttl_sec;
+            self.capacity = capacity;
+            self.zip = ZipBuffer::new(n, use_ctx, ttl_sec, capacity);
+            if n_changed {
+                self.emit_agent_spec_updated();
+            }
+        }
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), AgentError> {
+        self.zip.reset();
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let Some(inputs) = self.zip.push(&ctx, &pin, value)? else {
+            return Ok(());
+        };
+
+        let arr = product_array(inputs)?;
+        self.output(ctx, PIN_ARRAY, AgentValue::array(arr)).await
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SetOp {
+    Union,
+    Intersection,
+    Difference,
+    SymmetricDifference,
+}
+
+/// Parses `start:end` slice notation. Empty bounds are None.
+fn parse_slice(slice: &str) -> Result<(Option<i64>, Option<i64>), AgentError> {
+    let parse_bound = |s: &str| -> Result<Option<i64>, AgentError> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Ok(None);
+        }
+        s.parse::<i64>()
+            .map(Some)
+            .map_err(|e| AgentError::InvalidConfig(format!("Invalid slice '{}': {}", slice, e)))
+    };
+    match slice
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split_once(':')
+    {
+        Some((start, end)) => Ok((parse_bound(start)?, parse_bound(end)?)),
+        None => Err(AgentError::InvalidConfig(format!(
+            "Invalid slice '{}': expected start:end",
+            slice
+        ))),
+    }
+}
+
+fn flatten_into(out: &mut Vector<AgentValue>, arr: Vector<AgentValue>, depth: i64) {
+    for item in arr {
+        match item {
+            AgentValue::Array(inner) if depth != 0 => flatten_into(out, inner, depth - 1),
+            other => out.push_back(other),
+        }
+    }
+}
+
+/// Returns a string identifying a value for equality checks (its canonical JSON form).
+/// Missing keys are identified as null.
+fn identity_key(value: Option<&AgentValue>) -> String {
+    value
+        .and_then(|v| serde_json::to_string(v).ok())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Applies a set operation to two arrays, comparing items by the value at target_keys.
+/// Results keep first-seen order and contain no duplicates.
+fn set_op(
+    op: SetOp,
+    a: Vector<AgentValue>,
+    b: Vector<AgentValue>,
+    target_keys: &[String],
+) -> Vector<AgentValue> {
+    let key = |item: &AgentValue| identity_key(get_nested_value(item, target_keys));
+    let a_keys: HashSet<String> = a.iter().map(key).collect();
+    let b_keys: HashSet<String> = b.iter().map(key).collect();
+
+    let mut seen = HashSet::new();
+    let mut out = Vector::new();
+    let mut emit = |items: Vector<AgentValue>, keep: &dyn Fn(&str) -> bool| {
+        for item in items {
+            let k = key(&item);
+            if keep(&k) && seen.insert(k) {
+                out.push_back(item);
+            }
+        }
+    };
+    match op {
+        SetOp::Union => {
+            emit(a, &|_| true);
+            emit(b, &|_| true);
+        }
+        SetOp::Intersection => emit(a, &|k| b_keys.contains(k)),
+        SetOp::Difference => emit(a, &|k| !b_keys.contains(k)),
+        SetOp::SymmetricDifference => {
+            emit(a, &|k| !b_keys.contains(k));
+            emit(b, &|k| !a_keys.contains(k));
+        }
+    }
+    out
+}
+
+/// Upper bound on the length of ArrayRange, ArrayRepeat and ArrayProduct output, to catch runaway
+/// configs and inputs.
+const MAX_RANGE_LEN: f64 = 1_000_000.0;
+
+/// Builds the ArrayRange array from start up to (but not including) stop.
+fn range_array(start: f64, stop: f64, step: f64) -> Result<Vector<AgentValue>, AgentError> {
+    if step == 0.0 || !step.is_finite() || !start.is_finite() || !stop.is_finite() {
+        return Err(AgentError::InvalidConfig(format!(
+            "Invalid range: start={}, stop={}, step={}",
+            start, stop, step
+        )));
+    }
+
+    let len = ((stop - start) / step).ceil().max(0.0);
+    if len > MAX_RANGE_LEN {
+        return Err(AgentError::InvalidConfig(format!(
+            "Range is too long: {} items (max {})",
+            len, MAX_RANGE_LEN
+        )));
+    }
+
+    let integral = start.fract() == 0.0 && stop.fract() == 0.0 && step.fract() == 0.0;
+    Ok((0..len as usize)
+        .map(|i| {
+            let x = start + step * i as f64;
+            if integral {
+                AgentValue::integer(x as i64)
+            } else {
+                AgentValue::number(x)
+            }
+        })
+        .collect())
+}
+
+/// Builds the ArrayRepeat array holding value n times.
+fn repeat_array(value: AgentValue, n: usize) -> Result<Vector<AgentValue>, AgentError> {
+    if n as f64 > MAX_RANGE_LEN {
+        return Err(AgentError::InvalidValue(format!(
+            "Repeat is too long: {} items (max {})",
+            n, MAX_RANGE_LEN
+        )));
+    }
+    Ok(std::iter::repeat_n(value, n).collect())
+}
+
+/// Builds the ArrayProduct array of arrays, taking one item from every input.
+fn product_array(inputs: Vector<AgentValue>) -> Result<Vector<AgentValue>, AgentError> {
+    let inputs: Vec<Vector<AgentValue>> = inputs
+        .into_iter()
+        .map(|input| match input {
+            AgentValue::Array(arr) => arr,
+            other => vector![other],
+        })
+        .collect();
+
+    let len = inputs
+        .iter()
+        .try_fold(1usize, |len, items| len.checked_mul(items.len()));
+    if len.is_none_or(|len| len as f64 > MAX_RANGE_LEN) {
+        return Err(AgentError::InvalidValue(format!(
+            "Product is too large: more than {} items",
+            MAX_RANGE_LEN
+        )));
+    }
+
+    let mut product: Vector<Vector<AgentValue>> = vector![Vector::new()];
+    for items in inputs {
+        product = product
+            .into_iter()
+            .flat_map(|prefix| {
+                items.iter().map(move |item| {
+                    let mut tuple = prefix.clone();
+                    tuple.push_back(item.clone());
+                    tuple
+                })
+            })
+            .collect();
+    }
+    Ok(product.into_iter().map(AgentValue::array).collect())
+}
+
+/// Converts between array-of-arrays, records and columns layouts for ArrayTranspose.
+fn transpose(value: AgentValue, strict: bool) -> Result<AgentValue, AgentError> {
+    let ragged = || AgentError::InvalidValue("Cannot transpose ragged input".to_string());
+
+    match value {
+        AgentValue::Array(rows) if rows.iter().all(|r| r.is_array()) => {
+            let rows: Vec<Vector<AgentValue>> = rows
+                .into_iter()
+                .map(|r| match r {
+                    AgentValue::Array(arr) => arr,
+                    _ => unreachable!(),
+                })
+                .collect();
+            let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+            if strict && rows.iter().any(|r| r.len() != width) {
+                return Err(ragged());
+            }
+            let columns = (0..width)
+                .map(|i| {
+                    AgentValue::array(
+                        rows.iter()
+                            .map(|r| r.get(i).cloned().unwrap_or(AgentValue::Unit))
+                            .collect(),
+                    )
+                })
+                .collect();
+            Ok(AgentValue::array(columns))
+        }
+        AgentValue::Array(records) if records.iter().all(|r| r.is_object()) => {
+            let mut keys: Vec<String> = Vec::new();
+            let mut key_set = HashSet::new();
+            for record in records.iter() {
+                if let AgentValue::Object(obj) = record {
+                    for key in obj.keys() {
+                        if key_set.insert(key.clone()) {
+                            keys.push(key.clone());
+                        }
+                    }
+                }
+            }
+            let mut columns = HashMap::new();
+            for key in keys {
+                let mut column = Vector::new();
+                for record in records.iter() {
+                    match record.get(&key) {
+                        Some(v) => column.push_back(v.clone()),
+                        None if strict => return Err(ragged()),
+                        None => column.push_back(AgentValue::Unit),
+                    }
+                }
+                columns.insert(key, AgentValue::array(column));
+            }
+            Ok(AgentValue::object(columns))
+        }
+        AgentValue::Object(columns) if columns.values().all(|c| c.is_array()) => {
+            let height = columns
+                .values()
+                .filter_map(|c| c.as_array().map(|arr| arr.len()))
+                .max()
+                .unwrap_or(0);
+            let mut records = vec![HashMap::new(); height];
+            for (key, column) in columns {
+                let AgentValue::Array(column) = column else {
+                    unreachable!()
+                };
+                if strict && column.len() != height {
+                    return Err(ragged());
+                }
+                for (record, v) in records.iter_mut().zip(column) {
+                    record.insert(key.clone(), v);
+                }
+            }
+            Ok(AgentValue::array(
+                records.into_iter().map(AgentValue::object).collect(),
+            ))
+        }
+        _ => Err(AgentError::InvalidValue(
+            "Transpose expects an array of arrays, an array of objects or an object of arrays"
+                .to_string(),
+        )),
+    }
+}
+
+/// Parses a comma-separated list of percentiles between 0 and 100.
+fn parse_percentiles(s: &str) -> Result<Vec<f64>, AgentError> {
+    s.split(',')
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .map(|p| match p.parse::<f64>() {
+            Ok(x) if (0.0..=100.0).contains(&x) => Ok(x),
+            _ => Err(AgentError::InvalidConfig(format!(
+                "Invalid percentile '{}': expected a number between 0 and 100",
+                p
+            ))),
+        })
+        .collect()
+}
+
+/// Computes the ArrayStats summary object of numbers.
+fn array_stats(mut numbers: Vec<f64>, percentiles: &[f64]) -> Result<AgentValue, AgentError> {
+    numbers.sort_by(|a, b| a.total_cmp(b));
+
+    let count = numbers.len();
+    let sum: f64 = numbers.iter().sum();
+    let number_or_null = |x: Option<f64>| x.map(AgentValue::number).unwrap_or(AgentValue::Unit);
+
+    let mean = (count > 0).then(|| sum / count as f64);
+    let variance =
+        mean.map(|m| numbers.iter().map(|x| (x - m).powi(2)).sum::<f64>() / count as f64);
+
+    let mut stats = AgentValue::object_default();
+    stats.set("count".to_string(), AgentValue::integer(count as i64))?;
+    stats.set("sum".to_string(), AgentValue::number(sum))?;
+    stats.set("mean".to_string(), number_or_null(mean))?;
+    stats.set("min".to_string(), number_or_null(numbers.first().copied()))?;
+    stats.set("max".to_string(), number_or_null(numbers.last().copied()))?;
+    stats.set(
+        "median".to_string(),
+        number_or_null(percentile(&numbers, 50.0)),
+    )?;
+    stats.set("variance".to_string(), number_or_null(variance))?;
+    stats.set(
+        "stddev".to_string(),
+        number_or_null(variance.map(f64::sqrt)),
+    )?;
+    for &p in percentiles {
+        stats.set(percentile_key(p), number_or_null(percentile(&numbers, p)))?;
+    }
+    Ok(stats)
+}
+
+/// Names the stats entry of a percentile, e.g. `p99` or `p99_9`, so that it contains no `.`
+/// and can be read back with a dotted key path.
+fn percentile_key(p: f64) -> String {
+    format!("p{}", p).replace('.', "_")
+}
+
+/// Returns the p-th percentile (0 to 100) of sorted numbers, interpolating linearly between ranks.
+fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let frac = rank - lower as f64;
+    Some(sorted[lower] + (sorted[upper] - sorted[lower]) * frac)
+}
+
+/// Groups items by the value at the key path. Items without a non-null value there are returned separately.
+fn group_by(
+    arr: Vector<AgentValue>,
+    target_keys: &[String],
+) -> (HashMap<String, AgentValue>, Vector<AgentValue>) {
+    let mut groups: HashMap<String, AgentValue> = HashMap::new();
+    let mut missing = Vector::new();
+    for item in arr {
+        let value = match get_nested_value(&item, target_keys) {
+            None | Some(AgentValue::Unit) => {
+                missing.push_back(item);
+                continue;
+            }
+            Some(value) => value,
+        };
+        groups
+            .entry(group_key(value))
+            .or_insert_with(AgentValue::array_default)
+            .as_array_mut()
+            .unwrap()
+            .push_back(item);
+    }
+    (groups, missing)
+}
+
+/// Converts a grouping key value into an object key.
+fn group_key(value: &AgentValue) -> String {
+    match value {
+        AgentValue::String(s) => s.to_string(),
+        v => v
+            .to_string()
+            .or_else(|| serde_json::to_string(v).ok())
+            .unwrap_or_default(),
+    }
+}
+
+/// Splits an array into `n` contiguous buckets of near-equal size; earlier buckets receive the remainder.
+fn partition_count(mut arr: Vector<AgentValue>, n: usize) -> Vector<AgentValue> {
+    let size = arr.len() / n;
+    let remainder = arr.len() % n;
+    (0..n)
+        .map(|i| {
+            let len = size + usize::from(i < remainder);
+            let rest = arr.split_off(len);
+            AgentValue::array(std::mem::replace(&mut arr, rest))
+        })
+        .collect()
+}
+
+/// Normalizes a value into a sort key for the given compare mode.
+/// Returns None for nulls and values that cannot be compared in that mode.
+fn sort_key(value: &AgentValue, mode: CompareMode) -> Option<AgentValue> {
+    match (mode, value) {
+        (_, AgentValue::Unit) => None,
+        (CompareMode::Auto, _) => Some(value.clone()),
+        (CompareMode::Numeric, _) => value
+            .to_number()
+            .filter(|n| !n.is_nan())
+            .map(AgentValue::number),
+        (CompareMode::Lexical | CompareMode::Natural, _) => value
+            .to_string()
+            .or_else(|| serde_json::to_string(value).ok())
+            .map(AgentValue::string),
+    }
+}
+
+fn compare_sort_keys(a: &AgentValue, b: &AgentValue, mode: CompareMode) -> Ordering {
+    match (a, b) {
+        (AgentValue::String(a), AgentValue::String(b)) if mode == CompareMode::Natural => {
+            natural_cmp(a, b)
+        }
+        (AgentValue::String(a), AgentValue::String(b)) => a.cmp(b),
+        (AgentValue::Boolean(a), AgentValue::Boolean(b)) => a.cmp(b),
+        (AgentValue::Integer(a), AgentValue::Integer(b)) => a.cmp(b),
+        (
+            AgentValue::Integer(_) | AgentValue::Number(_),
+            AgentValue::Integer(_) | AgentValue::Number(_),
+        ) => a
+            .as_f64()
+            .unwrap_or_default()
+            .total_cmp(&b.as_f64().unwrap_or_default()),
+        _ => type_rank(a).cmp(&type_rank(b)).then_with(|| {
+            // Same type without a natural order (arrays, objects, ...): fall back to their JSON form
+            let a = serde_json::to_string(a).unwrap_or_default();
+            let b = serde_json::to_string(b).unwrap_or_default();
+            a.cmp(&b)
+        }),
+    }
+}
+
+fn type_rank(value: &AgentValue) -> u8 {
+    match value {
+        AgentValue::Unit => 0,
+        AgentValue::Boolean(_) => 1,
+        AgentValue::Integer(_) | AgentValue::Number(_) => 2,
+        AgentValue::String(_) => 3,
+        AgentValue::Array(_) => 4,
+        AgentValue::Object(_) => 5,
+        _ => 6,
+    }
+}
+
+/// Compares strings treating runs of ASCII digits as numbers ("item2" < "item10").
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let mut da = String::new();
+                while let Some(c) = a.next_if(|c| c.is_ascii_digit()) {
+                    da.push(c);
+                }
+                let mut db = String::new();
+                while let Some(c) = b.next_if(|c| c.is_ascii_digit()) {
+                    db.push(c);
+                }
+                let ta = da.trim_start_matches('0');
+                let tb = db.trim_start_matches('0');
+                let ord = ta
+                    .len()
+                    .cmp(&tb.len())
+                    .then_with(|| ta.cmp(tb))
+                    .then_with(|| da.len().cmp(&db.len()));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(ca), Some(cb)) => {
+                let ord = ca.cmp(&cb);
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a.next();
+                b.next();
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use im::hashmap;
+
+    use super::*;
+
+    #[test]
+    fn test_take_partial() {
+        let pending = || {
+            let mut pending = PendingCollect::new(AgentContext::new(), 4);
+            pending.values[1] = Some(AgentValue::integer(1));
+            pending.values[3] = Some(AgentValue::integer(3));
+            pending.received_count = 2;
+            pending
+        };
+        let missing = AgentValue::array(vector![AgentValue::integer(0), AgentValue::integer(2)]);
+
+        assert_eq!(
+            pending().take_partial(PartialPolicy::Drop),
+            (None, missing.clone())
+        );
+        assert_eq!(
+            pending().take_partial(PartialPolicy::Unit),
+            (
+                Some(AgentValue::array(vector![
+                    AgentValue::unit(),
+                    AgentValue::integer(1),
+                    AgentValue::unit(),
+                    AgentValue::integer(3),
+                ])),
+                missing.clone()
+            )
+        );
+        assert_eq!(
+            pending().take_partial(PartialPolicy::Compact),
+            (
+                Some(AgentValue::array(vector![
+                    AgentValue::integer(1),
+                    AgentValue::integer(3),
+                ])),
+                missing
+            )
+        );
+
+        let mut complete = PendingCollect::new(AgentContext::new(), 1);
+        complete.values[0] = Some(AgentValue::integer(0));
+        complete.received_count = 1;
+        assert!(complete.is_complete());
+        assert_eq!(
+            complete.take_partial(PartialPolicy::Unit),
+            (
+                Some(AgentValue::array(vector![AgentValue::integer(0)])),
+                AgentValue::array(vector![])
+            )
+        );
+    }
+
+    #[test]
+    fn test_slice() {
+        assert_eq!(parse_slice(":").unwrap(), (None, None));
+        assert_eq!(parse_slice("-5:").unwrap(), (Some(-5), None));
+        assert_eq!(parse_slice("[1:-1]").unwrap(), (Some(1), Some(-1)));
+        assert!(parse_slice("3").is_err());
+        assert!(parse_slice("a:b").is_err());
+    }
+
+    #[test]
+    fn test_flatten_into() {
+        let nested = vector![
+            AgentValue::integer(1),
+            AgentValue::array(vector![
+                AgentValue::integer(2),
+                AgentValue::array(vector![AgentValue::integer(3)]),
+            ]),
+        ];
+
+        let mut out = Vector::new();
+        flatten_into(&mut out, nested.clone(), 1);
+        assert_eq!(
+            out,
+            vector![
+                AgentValue::integer(1),
+                AgentValue::integer(2),
+                AgentValue::array(vector![AgentValue::integer(3)]),
+            ]
+        );
+
+        let mut out = Vector::new();
+        flatten_into(&mut out, nested, -1);
+        assert_eq!(
+            out,
+            vector![
+                AgentValue::integer(1),
+                AgentValue::integer(2),
+                AgentValue::integer(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("file10", "file9"), Ordering::Greater);
+        assert_eq!(natural_cmp("a1b2", "a1b2"), Ordering::Equal);
+        assert_eq!(natural_cmp("a01", "a1"), Ordering::Greater);
+        assert_eq!(natural_cmp("abc", "abd"), Ordering::Less);
+        assert_eq!(natural_cmp("x", "x1"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_compare_sort_keys() {
+        let auto = CompareMode::Auto;
+        assert_eq!(
+            compare_sort_keys(&AgentValue::integer(2), &AgentValue::number(1.5), auto),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_sort_keys(&AgentValue::integer(10), &AgentValue::string("2"), auto),
+            Ordering::Less
+        );
+
+        // Numeric mode parses strings, lexical mode stringifies numbers
+        let a = sort_key(&AgentValue::string("10"), CompareMode::Numeric).unwrap();
+        let b = sort_key(&AgentValue::integer(9), CompareMode::Numeric).unwrap();
+        assert_eq!(
+            compare_sort_keys(&a, &b, CompareMode::Numeric),
+            Ordering::Greater
+        );
+        let a = sort_key(&AgentValue::integer(10), CompareMode::Lexical).unwrap();
+        let b = sort_key(&AgentValue::integer(9), CompareMode::Lexical).unwrap();
+        assert_eq!(
+            compare_sort_keys(&a, &b, CompareMode::Lexical),
+            Ordering::Less
+        );
+
+        assert_eq!(sort_key(&AgentValue::unit(), auto), None);
+        assert_eq!(
+            sort_key(&AgentValue::string("x"), CompareMode::Numeric),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reducer_fold() {
+        let fold_all = |reducer: Reducer, key: &[&str], items: Vec<AgentValue>| {
+            let key: Vec<String> = key.iter().map(|s| s.to_string()).collect();
+            items
+                .into_iter()
+                .try_fold(None, |acc, item| reducer.fold(&key, acc, item).map(Some))
+        };
+        let ints = |xs: &[i64]| {
+            xs.iter()
+                .map(|&x| AgentValue::integer(x))
+                .collect::<Vec<_>>()
+        };
+        let item = |name: &str, score: i64| {
+            AgentValue::object(hashmap! {
+                "name".to_string() => AgentValue::string(name),
+                "score".to_string() => AgentValue::integer(score),
+            })
+        };
+
+        assert_eq!(
+            fold_all(Reducer::Sum, &[], ints(&[1, 2, 3])).unwrap(),
+            Some(AgentValue::integer(6))
+        );
+        assert_eq!(
+            fold_all(Reducer::Sum, &[], ints(&[i64::MAX, 1])).unwrap(),
+            Some(AgentValue::number(i64::MAX as f64 + 1.0))
+        );
+        assert!(
+            fold_all(
+                Reducer::Sum,
+                &[],
+                vec![AgentValue::integer(1), AgentValue::string("a")]
+            )
+            .is_err()
+        );
+        assert_eq!(
+            fold_all(Reducer::Count, &[], ints(&[5, 5, 5])).unwrap(),
+            Some(AgentValue::integer(3))
+        );
+        assert_eq!(
+            fold_all(Reducer::Min, &[], ints(&[3, 1, 2])).unwrap(),
+            Some(AgentValue::integer(1))
+        );
+        let items = vec![item("a", 2), item("b", 5), item("c", 1)];
+        assert_eq!(
+            fold_all(Reducer::Max, &["score"], items.clone()).unwrap(),
+            Some(item("b", 5))
+        );
+        assert_eq!(
+            fold_all(Reducer::Min, &["score"], items).unwrap(),
+            Some(item("c", 1))
+        );
+        assert_eq!(
+            fold_all(
+                Reducer::Concat,
+                &[],
+                vec![AgentValue::string("a"), AgentValue::integer(1)]
+            )
+            .unwrap(),
+            Some(AgentValue::string("a1"))
+        );
+        assert_eq!(
+            fold_all(
+                Reducer::Concat,
+                &[],
+                vec![
+                    AgentValue::integer(1),
+                    AgentValue::array(vector![AgentValue::integer(2), AgentValue::integer(3)])
+                ]
+            )
+            .unwrap(),
+            Some(AgentValue::array(vector![
+                AgentValue::integer(1),
+                AgentValue::integer(2),
+                AgentValue::integer(3)
+            ]))
+        );
+        assert_eq!(
+            fold_all(Reducer::Merge, &[], vec![item("a", 1), item("b", 2)]).unwrap(),
+            Some(item("b", 2))
+        );
+        assert!(fold_all(Reducer::Merge, &[], ints(&[1, 2])).is_err());
+        assert_eq!(
+            fold_all(Reducer::First, &[], ints(&[1, 2])).unwrap(),
+            Some(AgentValue::integer(1))
+        );
+        assert_eq!(
+            fold_all(Reducer::Last, &[], ints(&[1, 2])).unwrap(),
+            Some(AgentValue::integer(2))
+        );
+    }
+
+    #[test]
+    fn test_reducer_reduce_empty() {
+        let reduce_empty = |reducer: Reducer| reducer.reduce(&[], Vector::new()).unwrap();
+
+        assert_eq!(reduce_empty(Reducer::Sum), AgentValue::integer(0));
+        assert_eq!(reduce_empty(Reducer::Count), AgentValue::integer(0));
+        assert_eq!(reduce_empty(Reducer::Concat), AgentValue::array_default());
+        assert_eq!(reduce_empty(Reducer::Merge), AgentValue::object_default());
+        assert_eq!(reduce_empty(Reducer::Min), AgentValue::Unit);
+        assert_eq!(reduce_empty(Reducer::Max), AgentValue::Unit);
+        assert_eq!(reduce_empty(Reducer::First), AgentValue::Unit);
+        assert_eq!(reduce_empty(Reducer::Last), AgentValue::Unit);
+
+        let ints = (1..=3).map(AgentValue::integer);
+        assert_eq!(
+            Reducer::Sum.reduce(&[], ints.clone()).unwrap(),
+            AgentValue::integer(6)
+        );
+        assert_eq!(
+            Reducer::Count.reduce(&[], ints).unwrap(),
+            AgentValue::integer(3)
+        );
+    }
+
+    #[test]
+    fn test_pending_reduce() {
+        let mut pending = PendingReduce::default();
+        let push = |pending: &mut PendingReduce, idx: usize, value: &str| {
+            pending.push(idx, AgentValue::string(value), Reducer::Concat, &[])
+        };
+
+        // Out of order items are held until the preceding ones arrive
+        push(&mut pending, 2, "c").unwrap();
+        push(&mut pending, 1, "b").unwrap();
+        assert_eq!(pending.next, 0);
+        assert!(pending.acc.is_none());
+        push(&mut pending, 0, "a").unwrap();
+        assert_eq!(pending.next, 3);
+        assert_eq!(pending.acc, Some(AgentValue::string("abc")));
+
+        // Duplicates of folded items are ignored
+        push(&mut pending, 1, "x").unwrap();
+        assert_eq!(pending.acc, Some(AgentValue::string("abc")));
+
+        // A failing item is skipped and the following items are still folded
+        let mut pending = PendingReduce::default();
+        pending
+            .push(1, AgentValue::string("x"), Reducer::Sum, &[])
+            .unwrap();
+        pending
+            .push(2, AgentValue::integer(2), Reducer::Sum, &[])
+            .unwrap();
+        assert!(
+            pending
+                .push(0, AgentValue::integer(1), Reducer::Sum, &[])
+                .is_err()
+        );
+        assert_eq!(pending.next, 3);
+        assert_eq!(pending.acc, Some(AgentValue::integer(3)));
+    }
+
+    #[test]
+    fn test_set_op() {
+        let ints = |xs: &[i64]| {
+            xs.iter()
+                .map(|&x| AgentValue::integer(x))
+                .collect::<Vector<_>>()
+        };
+        let a = ints(&[1, 2, 2, 3]);
+        let b = ints(&[3, 4, 1, 5]);
+
+        assert_eq!(
+            set_op(SetOp::Union, a.clone(), b.clone(), &[]),
+            ints(&[1, 2, 3, 4, 5])
+        );
+        assert_eq!(
+            set_op(SetOp::Intersection, a.clone(), b.clone(), &[]),
+            ints(&[1, 3])
+        );
+        assert_eq!(
+            set_op(SetOp::Difference, a.clone(), b.clone(), &[]),
+            ints(&[2])
+        );
+        assert_eq!(
+            set_op(SetOp::SymmetricDifference, a, b, &[]),
+            ints(&[2, 4, 5])
+        );
+
+        // By key path, keeping the first-seen item
+        let item = |id: i64, name: &str| {
+            let mut obj = AgentValue::object_default();
+            obj.set("id".to_string(), AgentValue::integer(id)).unwrap();
+            obj.set("name".to_string(), AgentValue::string(name))
+                .unwrap();
+            obj
+        };
+        let keys = vec!["id".to_string()];
+        let a = vector![item(1, "a"), item(2, "b")];
+        let b = vector![item(2, "c"), item(3, "d")];
+        assert_eq!(
+            set_op(SetOp::Union, a.clone(), b.clone(), &keys),
+            vector![item(1, "a"), item(2, "b"), item(3, "d")]
+        );
+        assert_eq!(
+            set_op(SetOp::Intersection, a, b, &keys),
+            vector![item(2, "b")]
+        );
+    }
+
+    #[test]
+    fn test_array_stats() {
+        assert_eq!(parse_percentiles("25, 99.9").unwrap(), vec![25.0, 99.9]);
+        assert!(parse_percentiles("101").is_err());
+
+        let sorted = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(percentile(&sorted, 0.0), Some(1.0));
+        assert_eq!(percentile(&sorted, 50.0), Some(2.5));
+        assert_eq!(percentile(&sorted, 100.0), Some(4.0));
+        assert_eq!(percentile(&[], 50.0), None);
+
+        let stats =
+            array_stats(vec![4.0, 2.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0], &[25.0, 99.9]).unwrap();
+        assert_eq!(stats.get("count"), Some(&AgentValue::integer(8)));
+        assert_eq!(stats.get("sum"), Some(&AgentValue::number(40.0)));
+        assert_eq!(stats.get("mean"), Some(&AgentValue::number(5.0)));
+        assert_eq!(stats.get("min"), Some(&AgentValue::number(2.0)));
+        assert_eq!(stats.get("median"), Some(&AgentValue::number(4.5)));
+        assert_eq!(stats.get("stddev"), Some(&AgentValue::number(2.0)));
+        assert_eq!(stats.get("p25"), Some(&AgentValue::number(4.0)));
+        assert!(stats.get("p99_9").is_some());
+        assert_eq!(stats.get("p99.9"), None);
+
+        let empty = array_stats(Vec::new(), &[]).unwrap();
+        assert_eq!(empty.get("count"), Some(&AgentValue::integer(0)));
+        assert_eq!(empty.get("mean"), Some(&AgentValue::Unit));
+    }
+
+    #[test]
+    fn test_range_array() {
+        let ints = |xs: &[i64]| {
+            xs.iter()
+                .map(|&x| AgentValue::integer(x))
+                .collect::<Vector<_>>()
+        };
+        assert_eq!(range_array(0.0, 5.0, 2.0).unwrap(), ints(&[0, 2, 4]));
+        assert_eq!(range_array(3.0, 0.0, -1.0).unwrap(), ints(&[3, 2, 1]));
+        assert_eq!(range_array(3.0, 0.0, 1.0).unwrap(), ints(&[]));
+        assert_eq!(
+            range_array(0.0, 1.0, 0.5).unwrap(),
+            vector![AgentValue::number(0.0), AgentValue::number(0.5)]
+        );
+        assert!(range_array(0.0, 1.0, 0.0).is_err());
+        assert!(range_array(0.0, 1e9, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_repeat_and_product_array() {
+        let ints =
+            |xs: &[i64]| AgentValue::array(xs.iter().map(|&x| AgentValue::integer(x)).collect());
+        assert_eq!(
+            repeat_array(AgentValue::integer(1), 3).unwrap(),
+            Vector::from(vec![AgentValue::integer(1); 3])
+        );
+        assert!(repeat_array(AgentValue::integer(1), 2_000_000).is_err());
+
+        assert_eq!(
+            product_array(vector![
+                ints(&[1, 2]),
+                AgentValue::integer(3),
+                ints(&[4, 5])
+            ])
+            .unwrap(),
+            vector![
+                ints(&[1, 3, 4]),
+                ints(&[1, 3, 5]),
+                ints(&[2, 3, 4]),
+                ints(&[2, 3, 5])
+            ]
+        );
+        assert_eq!(
+            product_array(vector![ints(&[1]), ints(&[])]).unwrap(),
+            vector![]
+        );
+
+        let thousand = AgentValue::array((0..1000).map(AgentValue::integer).collect());
+        assert_eq!(
+            product_array(vector![thousand.clone(), ints(&[1, 2])])
+                .unwrap()
+                .len(),
+            2000
+        );
+        assert!(product_array(vector![thousand.clone(), thousand.clone(), ints(&[1, 2])]).is_err());
+    }
+
+    #[test]
+    fn test_group_by() {
+        let item = |kind: Option<AgentValue>| {
+            let mut obj = HashMap::new();
+            if let Some(kind) = kind {
+                obj.insert("kind".to_string(), kind);
+            }
+            AgentValue::object(obj)
+        };
+        let a = item(Some(AgentValue::string("a")));
+        let null_string = item(Some(AgentValue::string("null")));
+        let unit = item(Some(AgentValue::unit()));
+        let one = item(Some(AgentValue::integer(1)));
+        let none = item(None);
+
+        let arr = vector![
+            a.clone(),
+            none.clone(),
+            null_string.clone(),
+            one.clone(),
+            unit.clone(),
+            a.clone()
+        ];
+        let (groups, missing) = group_by(arr, &["kind".to_string()]);
+        assert_eq!(
+            groups,
+            hashmap! {
+                "a".to_string() => AgentValue::array(vector![a.clone(), a.clone()]),
+                "null".to_string() => AgentValue::array(vector![null_string]),
+                "1".to_string() => AgentValue::array(vector![one]),
+            }
+        );
+        assert_eq!(missing, vector![none, unit]);
+
+        let (groups, missing) = group_by(vector![AgentValue::integer(1)], &[] as &[String]);
+        assert_eq!(groups.len(), 1);
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn test_partition_count() {
+        let ints =
+            |xs: &[i64]| AgentValue::array(xs.iter().map(|&x| AgentValue::integer(x)).collect());
+
+        let arr = (1..=5).map(AgentValue::integer).collect();
+        assert_eq!(
+            partition_count(arr, 3),
+            vector![ints(&[1, 2]), ints(&[3, 4]), ints(&[5])]
+        );
+        assert_eq!(
+            partition_count(vector![AgentValue::integer(1)], 3),
+            vector![ints(&[1]), ints(&[]), ints(&[])]
+        );
+    }
+
+    #[test]
+    fn test_transpose() {
+        let ints =
+            |xs: &[i64]| AgentValue::array(xs.iter().map(|&x| AgentValue::integer(x)).collect());
+
+        let rows = AgentValue::array(vector![ints(&[1, 2, 3]), ints(&[4, 5, 6])]);
+        let columns = AgentValue::array(vector![ints(&[1, 4]), ints(&[2, 5]), ints(&[3, 6])]);
+        assert_eq!(transpose(rows.clone(), true).unwrap(), columns);
+        assert_eq!(transpose(columns, true).unwrap(), rows);
+
+        let ragged = AgentValue::array(vector![ints(&[1, 2]), ints(&[3])]);
+        assert!(transpose(ragged.clone(), true).is_err());
+        assert_eq!(
+            transpose(ragged, false).unwrap(),
+            AgentValue::array(vector![
+                ints(&[1, 3]),
+                AgentValue::array(vector![AgentValue::integer(2), AgentValue::Unit]),
+            ])
+        );
+
+        // Records <-> columns
+        let record = |a: i64, b: i64| {
+            let mut obj = AgentValue::object_default();
+            obj.set("a".to_string(), AgentValue::integer(a)).unwrap();
+            obj.set("b".to_string(), AgentValue::integer(b)).unwrap();
+            obj
+        };
+        let records = AgentValue::array(vector![record(1, 2), record(3, 4)]);
+        let mut columns = AgentValue::object_default();
+        columns.set("a".to_string(), ints(&[1, 3])).unwrap();
+        columns.set("b".to_string(), ints(&[2, 4])).unwrap();
+        assert_eq!(transpose(records.clone(), true).unwrap(), columns);
+        assert_eq!(transpose(columns, true).unwrap(), records);
+
+        assert!(transpose(AgentValue::integer(1), false).is_err());
+    }
+}
@@ -2,23 +2,44 @@ use std::time::Duration;
 use std::{collections::VecDeque, vec};
 
 use agent_stream_kit::{
-    ASKit, AgentConfigSpec, AgentConfigSpecs, AgentConfigs, AgentContext, AgentData, AgentError,
-    AgentOutput, AgentSpec, AgentValue, AsAgent, askit_agent, async_trait,
+    ASKit, Agent, AgentConfigSpec, AgentConfigSpecs, AgentConfigs, AgentContext, AgentData,
+    AgentError, AgentOutput, AgentSpec, AgentValue, AsAgent, askit_agent, async_trait,
 };
 use im::{HashMap, Vector};
 use mini_moka::sync::Cache;
 
+use crate::array::{ZipBuffer, natural_cmp, zip_configs};
+use crate::index::resolve_index;
+use crate::query::Query;
+
 const CATEGORY: &str = "Std/Data";
 
+const PIN_ARRAY: &str = "array";
 const PIN_IN1: &str = "in1";
+const PIN_INVALID: &str = "invalid";
 const PIN_IN2: &str = "in2";
 const PIN_JSON: &str = "json";
 const PIN_OBJECT: &str = "object";
+const PIN_PATCH: &str = "patch";
+const PIN_VALID: &str = "valid";
 const PIN_VALUE: &str = "value";
 
+const CONFIG_ARRAYS: &str = "arrays";
+const CONFIG_SEPARATOR: &str = "separator";
+const CONFIG_ARRAY_KEY: &str = "array_key";
+const CONFIG_CONFLICT: &str = "conflict";
+const CONFIG_FORMAT: &str = "format";
 const CONFIG_KEY: &str = "key";
+const CONFIG_MATCH: &str = "match";
+const CONFIG_QUERY: &str = "query";
 const CONFIG_VALUE: &str = "value";
 const CONFIG_N: &str = "n";
+const CONFIG_OMIT: &str = "omit";
+const CONFIG_PICK: &str = "pick";
+const CONFIG_PRUNE: &str = "prune";
+const CONFIG_RENAME: &str = "rename";
+const CONFIG_SCHEMA: &str = "schema";
+const CONFIG_SCHEMA_TEXT: &str = "schema_text";
 const CONFIG_USE_CTX: &str = "use_ctx";
 const CONFIG_TTL_SECONDS: &str = "ttl_sec";
 const CONFIG_CAPACITY: &str = "capacity";
@@ -43,11 +64,7 @@ impl GetValueAgent {
             .as_ref()
             .map(|cfg| cfg.get_string_or_default(CONFIG_KEY))
             .unwrap_or_default();
-        if key_str.is_empty() {
-            return Ok(Vec::new());
-        }
-        let target_keys = key_str.split('.').map(|s| s.to_string()).collect();
-        Ok(target_keys)
+        Ok(parse_key_path(&key_str))
     }
 }
 
@@ -101,6 +118,87 @@ impl AsAgent for GetValueAgent {
     }
 }
 
+// Query
+//
+// Selects values with a JSON Pointer (`/items/0/name`) or a JSONPath
+// (`$.items[?(@.score > 0.5)].name`). `match` chooses the output: `first` emits the first match
+// (or unit), `all` emits an array of all matches, and `auto` emits the single match of a JSON Pointer
+// or a definite JSONPath and an array otherwise.
+#[askit_agent(
+    title = "Query",
+    category = CATEGORY,
+    inputs = [PIN_VALUE],
+    outputs = [PIN_VALUE],
+    string_config(name = CONFIG_QUERY, description = "JSON Pointer (/a/0) or JSONPath ($.a[*].b)"),
+    string_config(name = CONFIG_MATCH, default = "auto", description = "auto, first or all"),
+)]
+struct QueryAgent {
+    data: AgentData,
+    query: Query,
+    all: bool,
+}
+
+impl QueryAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<(Query, bool), AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let query = Query::parse(&cfg.get_string_or_default(CONFIG_QUERY))?;
+
+        let all = match cfg.get_string_or(CONFIG_MATCH, "auto").as_str() {
+            "auto" => !query.is_definite(),
+            "first" => false,
+            "all" => true,
+            other => {
+                return Err(AgentError::InvalidConfig(format!(
+                    "Invalid match '{}': expected auto, first or all",
+                    other
+                )));
+            }
+        };
+
+        Ok((query, all))
+    }
+}
+
+#[async_trait]
+impl AsAgent for QueryAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (query, all) = Self::update_spec(&mut spec)?;
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+            query,
+            all,
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        (self.query, self.all) = Self::update_spec(&mut self.data.spec)?;
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let matches = self.query.select(&value);
+
+        let output_value = if self.all {
+            AgentValue::array(matches.into_iter().cloned().collect())
+        } else {
+            matches
+                .first()
+                .map(|v| (*v).clone())
+                .unwrap_or(AgentValue::Unit)
+        };
+
+        self.output(ctx, PIN_VALUE, output_value).await
+    }
+}
+
 // Set Value
 #[askit_agent(
     title = "Set Value",
@@ -123,7 +221,7 @@ impl SetValueAgent {
             .as_ref()
             .map(|cfg| cfg.get_string_or_default(CONFIG_KEY))
             .unwrap_or_default();
-        let target_keys = key_str.split('.').map(|s| s.to_string()).collect();
+        let target_keys = parse_key_path(&key_str);
         let target_value = spec
             .configs
             .as_ref()
@@ -166,6 +264,190 @@ impl AsAgent for SetValueAgent {
     }
 }
 
+// Delete Value
+//
+// Removes one or more comma-separated key paths from an object, or from each object in an array.
+// With `prune`, parents left empty by a removal are removed as well.
+#[askit_agent(
+    title = "Delete Value",
+    category = CATEGORY,
+    inputs = [PIN_VALUE],
+    outputs = [PIN_VALUE],
+    string_config(name = CONFIG_KEY, description = "comma-separated key paths"),
+    boolean_config(name = CONFIG_PRUNE),
+)]
+struct DeleteValueAgent {
+    data: AgentData,
+    target_keys: Vec<Vec<String>>,
+    prune: bool,
+}
+
+impl DeleteValueAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<(Vec<Vec<String>>, bool), AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+        let target_keys = parse_key_paths(&cfg.get_string_or_default(CONFIG_KEY));
+        let prune = cfg.get_bool_or_default(CONFIG_PRUNE);
+        Ok((target_keys, prune))
+    }
+}
+
+#[async_trait]
+impl AsAgent for DeleteValueAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (target_keys, prune) = Self::update_spec(&mut spec)?;
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+            target_keys,
+            prune,
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        (self.target_keys, self.prune) = Self::update_spec(&mut self.data.spec)?;
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let output_value = match value {
+            AgentValue::Array(arr) => AgentValue::Array(
+                arr.into_iter()
+                    .map(|item| delete_key_paths(item, &self.target_keys, self.prune))
+                    .collect(),
+            ),
+            other => delete_key_paths(other, &self.target_keys, self.prune),
+        };
+
+        self.output(ctx, PIN_VALUE, output_value).await
+    }
+}
+
+// Project
+//
+// Reshapes an object, or each object in an array: `pick` keeps only the listed key paths
+// (all if empty), `omit` then removes key paths, and `rename` moves values from the key paths
+// of its keys to the key paths of its values, e.g. `{"user.name": "name"}`.
+// Key paths are comma-separated. Values that are not objects are passed through unchanged.
+#[askit_agent(
+    title = "Project",
+    category = CATEGORY,
+    inputs = [PIN_VALUE],
+    outputs = [PIN_VALUE],
+    string_config(name = CONFIG_PICK, description = "comma-separated key paths"),
+    string_config(name = CONFIG_OMIT, description = "comma-separated key paths"),
+    object_config(name = CONFIG_RENAME),
+)]
+struct ProjectAgent {
+    data: AgentData,
+    projection: Projection,
+}
+
+struct Projection {
+    pick: Vec<Vec<String>>,
+    omit: Vec<Vec<String>>,
+    rename: Vec<(Vec<String>, Vec<String>)>,
+}
+
+impl ProjectAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<Projection, AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let pick = parse_key_paths(&cfg.get_string_or_default(CONFIG_PICK));
+        let omit = parse_key_paths(&cfg.get_string_or_default(CONFIG_OMIT));
+
+        let mut rename = Vec::new();
+        for (from, to) in cfg.get_object_or_default(CONFIG_RENAME) {
+            let Some(to) = to.as_str() else {
+                return Err(AgentError::InvalidConfig(format!(
+                    "rename: the new key path for '{}' must be a string",
+                    from
+                )));
+            };
+            rename.push((parse_key_path(&from), parse_key_path(to)));
+        }
+
+        Ok(Projection { pick, omit, rename })
+    }
+}
+
+impl Projection {
+    fn apply(&self, value: AgentValue) -> AgentValue {
+        if !value.is_object() {
+            return value;
+        }
+
+        let mut value = if self.pick.is_empty() {
+            value
+        } else {
+            let mut picked = AgentValue::object_default();
+            for keys in &self.pick {
+                if let Some(v) = get_nested_value(&value, keys) {
+                    set_nested_value(&mut picked, keys, v.clone());
+                }
+            }
+            picked
+        };
+
+        for keys in removal_order(&value, &self.omit) {
+            remove_nested_value(&mut value, &keys);
+        }
+
+        // Remove all sources first so that renames can swap keys
+        let moved: Vec<_> = self
+            .rename
+            .iter()
+            .filter_map(|(from, to)| Some((to, remove_nested_value(&mut value, from)?)))
+            .collect();
+        for (to, v) in moved {
+            set_nested_value(&mut value, to, v);
+        }
+
+        value
+    }
+}
+
+#[async_trait]
+impl AsAgent for ProjectAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let projection = Self::update_spec(&mut spec)?;
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+            projection,
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        self.projection = Self::update_spec(&mut self.data.spec)?;
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let output_value = match value {
+            AgentValue::Array(arr) => AgentValue::Array(
+                arr.into_iter()
+                    .map(|item| self.projection.apply(item))
+                    .collect(),
+            ),
+            other => self.projection.apply(other),
+        };
+
+        self.output(ctx, PIN_VALUE, output_value).await
+    }
+}
+
 // To Object
 #[askit_agent(
     title = "To Object",
@@ -186,11 +468,7 @@ impl ToObjectAgent {
             .as_ref()
             .map(|cfg| cfg.get_string_or_default(CONFIG_KEY))
             .unwrap_or_default();
-        if key_str.is_empty() {
-            return Ok(Vec::new());
-        }
-        let target_keys = key_str.split('.').map(|s| s.to_string()).collect();
-        Ok(target_keys)
+        Ok(parse_key_path(&key_str))
     }
 }
 
@@ -227,6 +505,475 @@ impl AsAgent for ToObjectAgent {
     }
 }
 
+// Validate
+//
+// Validates values against a JSON Schema (draft 2020-12) given as an object in `schema`,
+// or as JSON text in `schema_text` (which takes precedence when not empty).
+// Valid values are emitted on `valid`. Invalid ones are emitted on `invalid` as
+// `{ value, errors: [{ path, message }] }`, where `path` is a JSON Pointer into the value.
+#[askit_agent(
+    title = "Validate",
+    category = CATEGORY,
+    inputs = [PIN_VALUE],
+    outputs = [PIN_VALID, PIN_INVALID],
+    object_config(name = CONFIG_SCHEMA),
+    text_config(name = CONFIG_SCHEMA_TEXT),
+)]
+struct ValidateAgent {
+    data: AgentData,
+    validator: jsonschema::Validator,
+}
+
+impl ValidateAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<jsonschema::Validator, AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let schema_text = cfg.get_string_or_default(CONFIG_SCHEMA_TEXT);
+        let schema: serde_json::Value = if schema_text.trim().is_empty() {
+            let schema = cfg.get(CONFIG_SCHEMA).cloned().unwrap_or(AgentValue::Unit);
+            if schema.is_unit() {
+                serde_json::json!({})
+            } else {
+                serde_json::to_value(&schema)
+                    .map_err(|e| AgentError::InvalidConfig(format!("Invalid schema: {}", e)))?
+            }
+        } else {
+            serde_json::from_str(&schema_text)
+                .map_err(|e| AgentError::InvalidConfig(format!("Invalid schema JSON: {}", e)))?
+        };
+
+        jsonschema::draft202012::new(&schema)
+            .map_err(|e| AgentError::InvalidConfig(format!("Invalid schema: {}", e)))
+    }
+}
+
+#[async_trait]
+impl AsAgent for ValidateAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let validator = Self::update_spec(&mut spec)?;
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+            validator,
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        self.validator = Self::update_spec(&mut self.data.spec)?;
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let errors = validation_errors(&self.validator, &value)?;
+        if errors.is_empty() {
+            return self.output(ctx, PIN_VALID, value).await;
+        }
+
+        let mut invalid = AgentValue::object_default();
+        invalid.set("value".to_string(), value)?;
+        invalid.set("errors".to_string(), AgentValue::array(errors))?;
+        self.output(ctx, PIN_INVALID, invalid).await
+    }
+}
+
+// Apply Patch
+//
+// Holds a target value, replaced by each value arriving on `value`, and applies each patch
+// arriving on `patch` to it, emitting the updated target.
+// `format` is `json_patch` (RFC 6902, an array of operations), `merge_patch` (RFC 7386),
+// or `auto` (arrays are JSON Patches and anything else is a Merge Patch).
+// A JSON Patch that fails leaves the target unchanged.
+#[askit_agent(
+    title = "Apply Patch",
+    category = CATEGORY,
+    inputs = [PIN_VALUE, PIN_PATCH],
+    outputs = [PIN_VALUE],
+    string_config(name = CONFIG_FORMAT, default = "auto", description = "auto, json_patch or merge_patch"),
+)]
+struct ApplyPatchAgent {
+    data: AgentData,
+    target: serde_json::Value,
+}
+
+#[async_trait]
+impl AsAgent for ApplyPatchAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+            target: serde_json::Value::Null,
+        })
+    }
+
+    async fn stop(&mut self) -> Result<(), AgentError> {
+        self.target = serde_json::Value::Null;
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let value = to_json_value(&value)?;
+        if pin == PIN_VALUE {
+            self.target = value;
+            return Ok(());
+        }
+
+        let format = self.configs()?.get_string_or(CONFIG_FORMAT, "auto");
+        let is_json_patch = match format.as_str() {
+            "auto" => value.is_array(),
+            "json_patch" => true,
+            "merge_patch" => false,
+            other => {
+                return Err(AgentError::InvalidConfig(format!(
+                    "Invalid format '{}': expected auto, json_patch or merge_patch",
+                    other
+                )));
+            }
+        };
+
+        if is_json_patch {
+            let patch: json_patch::Patch = serde_json::from_value(value)
+                .map_err(|e| AgentError::InvalidValue(format!("Invalid JSON Patch: {}", e)))?;
+            json_patch::patch(&mut self.target, &patch)
+                .map_err(|e| AgentError::InvalidValue(format!("Failed to apply patch: {}", e)))?;
+        } else {
+            json_patch::merge(&mut self.target, &value);
+        }
+
+        let output_value = AgentValue::from_json(self.target.clone())?;
+        self.output(ctx, PIN_VALUE, output_value).await
+    }
+}
+
+// Diff
+//
+// Emits the patch that turns the value on in1 into the value on in2, as a JSON Patch
+// (RFC 6902) or a Merge Patch (RFC 7386) depending on `format`.
+// Inputs are paired like ZipToObject, including the `use_ctx` matching.
+#[askit_agent(
+    title = "Diff",
+    category = CATEGORY,
+    inputs = [PIN_IN1, PIN_IN2],
+    outputs = [PIN_PATCH],
+    string_config(name = CONFIG_FORMAT, default = "json_patch", description = "json_patch or merge_patch"),
+    boolean_config(name = CONFIG_USE_CTX),
+    integer_config(name = CONFIG_TTL_SECONDS, default = 60),
+    integer_config(name = CONFIG_CAPACITY, default = 1000),
+)]
+struct DiffAgent {
+    data: AgentData,
+    merge_patch: bool,
+    use_ctx: bool,
+    ttl_sec: u64,
+    capacity: u64,
+    zip: ZipBuffer,
+}
+
+impl DiffAgent {
+    fn update_spec(spec: &mut AgentSpec) -> Result<(bool, bool, u64, u64), AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let merge_patch = match cfg.get_string_or(CONFIG_FORMAT, "json_patch").as_str() {
+            "json_patch" => false,
+            "merge_patch" => true,
+            other => {
+                return Err(AgentError::InvalidConfig(format!(
+                    "Invalid format '{}': expected json_patch or merge_patch",
+                    other
+                )));
+            }
+        };
+
+        let (use_ctx, ttl_sec, capacity) = zip_configs(spec);
+
+        Ok((merge_patch, use_ctx, ttl_sec, capacity))
+    }
+}
+
+#[async_trait]
+impl AsAgent for DiffAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (merge_patch, use_ctx, ttl_sec, capacity) = Self::update_spec(&mut spec)?;
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+            merge_patch,
+            use_ctx,
+            ttl_sec,
+            capacity,
+            zip: ZipBuffer::new(2, use_ctx, ttl_sec, capacity),
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        let (merge_patch, use_ctx, ttl_sec, capacity) = Self::update_spec(&mut self.data.spec)?;
+        self.merge_patch = merge_patch;
+        if (use_ctx, ttl_sec, capacity) != (self.use_ctx, self.ttl_sec, self.capacity) {
+            self.use_ctx = use_ctx;
+            self.ttl_sec = ttl_sec;
+            self.capacity = capacity;
+            self.zip = ZipBuffer::new(2, use_ctx, ttl_sec, capacity);
+        }
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), AgentError> {
+        self.zip.reset();
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let Some(pair) = self.zip.push(&ctx, &pin, value)? else {
+            return Ok(());
+        };
+        let left = to_json_value(&pair[0])?;
+        let right = to_json_value(&pair[1])?;
+
+        let patch = if self.merge_patch {
+            merge_patch_diff(&left, &right)
+        } else {
+            serde_json::to_value(json_patch::diff(&left, &right)).map_err(|e| {
+                AgentError::InvalidValue(format!("Failed to serialize patch: {}", e))
+            })?
+        };
+
+        self.output(ctx, PIN_PATCH, AgentValue::from_json(patch)?)
+            .await
+    }
+}
+
+// Flatten Object
+//
+// Converts a nested object into a flat object keyed by key paths,
+// e.g. `{"a": {"b": 1}}` to `{"a.b": 1}`. With `arrays`, array items are flattened too, keyed by
+// index (`items.0.name`); otherwise arrays are kept as values. Empty objects and arrays are kept
+// as values.
+#[askit_agent(
+    title = "Flatten Object",
+    category = CATEGORY,
+    inputs = [PIN_VALUE],
+    outputs = [PIN_OBJECT],
+    string_config(name = CONFIG_SEPARATOR, default = "."),
+    boolean_config(name = CONFIG_ARRAYS, default = true),
+)]
+struct FlattenObjectAgent {
+    data: AgentData,
+}
+
+#[async_trait]
+impl AsAgent for FlattenObjectAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+        })
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let config = self.configs()?;
+        let separator = separator_config(config.get_string_or(CONFIG_SEPARATOR, "."))?;
+        let arrays = config.get_bool_or(CONFIG_ARRAYS, true);
+
+        let mut flat = HashMap::new();
+        flatten_value(&value, &mut Vec::new(), &separator, arrays, &mut flat);
+        self.output(ctx, PIN_OBJECT, AgentValue::object(flat)).await
+    }
+}
+
+// Unflatten Object
+//
+// Converts a flat object keyed by key paths back into a nested object, e.g. `{"a.b": 1}` to
+// `{"a": {"b": 1}}`. Keys are split by `separator` and set like Set Value, so consecutive numeric
+// segments starting at 0 create arrays.
+#[askit_agent(
+    title = "Unflatten Object",
+    category = CATEGORY,
+    inputs = [PIN_OBJECT],
+    outputs = [PIN_VALUE],
+    string_config(name = CONFIG_SEPARATOR, default = "."),
+)]
+struct UnflattenObjectAgent {
+    data: AgentData,
+}
+
+#[async_trait]
+impl AsAgent for UnflattenObjectAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+        })
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let separator = separator_config(self.configs()?.get_string_or(CONFIG_SEPARATOR, "."))?;
+
+        let AgentValue::Object(flat) = value else {
+            return Err(AgentError::InvalidValue(
+                "Unflatten Object expects an object".to_string(),
+            ));
+        };
+
+        let output_value = unflatten_object(flat, &separator);
+        self.output(ctx, PIN_VALUE, output_value).await
+    }
+}
+
+// Object Keys
+//
+// Emits the keys of an object as an array of strings, in key order.
+#[askit_agent(
+    title = "Object Keys",
+    category = CATEGORY,
+    inputs = [PIN_OBJECT],
+    outputs = [PIN_ARRAY],
+)]
+struct ObjectKeysAgent {
+    data: AgentData,
+}
+
+#[async_trait]
+impl AsAgent for ObjectKeysAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+        })
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let keys = sorted_entries(value)?
+            .into_iter()
+            .map(|(key, _)| AgentValue::string(key))
+            .collect();
+        self.output(ctx, PIN_ARRAY, AgentValue::array(keys)).await
+    }
+}
+
+// Object Values
+//
+// Emits the values of an object as an array, in key order.
+#[askit_agent(
+    title = "Object Values",
+    category = CATEGORY,
+    inputs = [PIN_OBJECT],
+    outputs = [PIN_ARRAY],
+)]
+struct ObjectValuesAgent {
+    data: AgentData,
+}
+
+#[async_trait]
+impl AsAgent for ObjectValuesAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+        })
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let values = sorted_entries(value)?.into_iter().map(|(_, v)| v).collect();
+        self.output(ctx, PIN_ARRAY, AgentValue::array(values)).await
+    }
+}
+
+// Object Entries
+//
+// Emits the entries of an object as an array of `{ key, value }` objects, in key order.
+#[askit_agent(
+    title = "Object Entries",
+    category = CATEGORY,
+    inputs = [PIN_OBJECT],
+    outputs = [PIN_ARRAY],
+)]
+struct ObjectEntriesAgent {
+    data: AgentData,
+}
+
+#[async_trait]
+impl AsAgent for ObjectEntriesAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+        })
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let entries = object_entries(value)?;
+        self.output(ctx, PIN_ARRAY, entries).await
+    }
+}
+
+// From Entries
+//
+// Builds an object from an array of entries, either `{ key, value }` objects or `[key, value]`
+// pairs. Non-string keys are converted to strings, and later entries overwrite earlier ones.
+#[askit_agent(
+    title = "From Entries",
+    category = CATEGORY,
+    inputs = [PIN_ARRAY],
+    outputs = [PIN_OBJECT],
+)]
+struct FromEntriesAgent {
+    data: AgentData,
+}
+
+#[async_trait]
+impl AsAgent for FromEntriesAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+        })
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let obj = from_entries(value)?;
+        self.output(ctx, PIN_OBJECT, obj).await
+    }
+}
+
 // To JSON
 #[askit_agent(
     title = "To JSON",
@@ -278,67 +1025,359 @@ impl AsAgent for FromJsonAgent {
         })
     }
 
-    async fn process(
-        &mut self,
-        ctx: AgentContext,
-        _pin: String,
-        value: AgentValue,
-    ) -> Result<(), AgentError> {
-        let s = value
-            .as_str()
-            .ok_or_else(|| AgentError::InvalidValue("not a string".to_string()))?;
-        let json_value: serde_json::Value =
-            serde_json::from_str(s).map_err(|e| AgentError::InvalidValue(e.to_string()))?;
-        let value = AgentValue::from_json(json_value)?;
-        self.output(ctx, PIN_VALUE, value).await?;
-        Ok(())
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let s = value
+            .as_str()
+            .ok_or_else(|| AgentError::InvalidValue("not a string".to_string()))?;
+        let json_value: serde_json::Value =
+            serde_json::from_str(s).map_err(|e| AgentError::InvalidValue(e.to_string()))?;
+        let value = AgentValue::from_json(json_value)?;
+        self.output(ctx, PIN_VALUE, value).await?;
+        Ok(())
+    }
+}
+
+/// Validates a value, returning `{ path, message }` objects for each error.
+fn validation_errors(
+    validator: &jsonschema::Validator,
+    value: &AgentValue,
+) -> Result<Vector<AgentValue>, AgentError> {
+    let instance = to_json_value(value)?;
+
+    let mut errors = Vector::new();
+    for error in validator.iter_errors(&instance) {
+        let mut entry = AgentValue::object_default();
+        entry.set(
+            "path".to_string(),
+            AgentValue::string(error.instance_path().to_string()),
+        )?;
+        entry.set("message".to_string(), AgentValue::string(error.to_string()))?;
+        errors.push_back(entry);
+    }
+    Ok(errors)
+}
+
+fn to_json_value(value: &AgentValue) -> Result<serde_json::Value, AgentError> {
+    serde_json::to_value(value)
+        .map_err(|e| AgentError::InvalidValue(format!("Failed to convert value to JSON: {}", e)))
+}
+
+/// Returns the RFC 7386 Merge Patch that turns left into right.
+/// Removed keys become null; note that a Merge Patch cannot set a value to null.
+fn merge_patch_diff(left: &serde_json::Value, right: &serde_json::Value) -> serde_json::Value {
+    match (left, right) {
+        (serde_json::Value::Object(l), serde_json::Value::Object(r)) => {
+            let mut patch = serde_json::Map::new();
+            for key in l.keys() {
+                if !r.contains_key(key) {
+                    patch.insert(key.clone(), serde_json::Value::Null);
+                }
+            }
+            for (key, rv) in r {
+                match l.get(key) {
+                    Some(lv) if lv == rv => {}
+                    Some(lv) => {
+                        patch.insert(key.clone(), merge_patch_diff(lv, rv));
+                    }
+                    None => {
+                        patch.insert(key.clone(), rv.clone());
+                    }
+                }
+            }
+            serde_json::Value::Object(patch)
+        }
+        _ => right.clone(),
+    }
+}
+
+fn separator_config(separator: String) -> Result<String, AgentError> {
+    if separator.is_empty() {
+        return Err(AgentError::InvalidConfig(
+            "separator must not be empty".to_string(),
+        ));
+    }
+    Ok(separator)
+}
+
+/// Inserts the leaves of value into out, keyed by their key paths joined with separator.
+fn flatten_value(
+    value: &AgentValue,
+    path: &mut Vec<String>,
+    separator: &str,
+    arrays: bool,
+    out: &mut HashMap<String, AgentValue>,
+) {
+    match value {
+        AgentValue::Object(obj) if !obj.is_empty() => {
+            for (key, v) in obj {
+                path.push(key.clone());
+                flatten_value(v, path, separator, arrays, out);
+                path.pop();
+            }
+        }
+        AgentValue::Array(arr) if arrays && !arr.is_empty() => {
+            for (i, v) in arr.iter().enumerate() {
+                path.push(i.to_string());
+                flatten_value(v, path, separator, arrays, out);
+                path.pop();
+            }
+        }
+        leaf => {
+            out.insert(path.join(separator), leaf.clone());
+        }
+    }
+}
+
+/// Rebuilds a nested object from an object keyed by key paths joined with separator.
+fn unflatten_object(flat: HashMap<String, AgentValue>, separator: &str) -> AgentValue {
+    // Sort so that conflicting keys resolve the same way every time, with numeric segments
+    // compared as numbers so that array items are appended in index order
+    let mut entries: Vec<_> = flat.into_iter().collect();
+    entries.sort_by(|a, b| natural_cmp(&a.0, &b.0));
+
+    let mut root = AgentValue::object_default();
+    for (key, v) in entries {
+        let keys: Vec<&str> = key.split(separator).collect();
+        set_nested_value(&mut root, &keys, v);
+    }
+    root
+}
+
+/// Returns the entries of an object sorted by key.
+fn sorted_entries(value: AgentValue) -> Result<Vec<(String, AgentValue)>, AgentError> {
+    let AgentValue::Object(obj) = value else {
+        return Err(AgentError::InvalidValue("Expected an object".to_string()));
+    };
+    let mut entries: Vec<_> = obj.into_iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(entries)
+}
+
+/// Returns the entries of an object as an array of `{ key, value }` objects, in key order.
+fn object_entries(value: AgentValue) -> Result<AgentValue, AgentError> {
+    let entries = sorted_entries(value)?
+        .into_iter()
+        .map(|(key, v)| {
+            AgentValue::object(HashMap::from(vec![
+                ("key".to_string(), AgentValue::string(key)),
+                ("value".to_string(), v),
+            ]))
+        })
+        .collect();
+    Ok(AgentValue::array(entries))
+}
+
+/// Builds an object from an array of `{ key, value }` objects or `[key, value]` pairs.
+fn from_entries(value: AgentValue) -> Result<AgentValue, AgentError> {
+    let AgentValue::Array(entries) = value else {
+        return Err(AgentError::InvalidValue(
+            "From Entries expects an array of entries".to_string(),
+        ));
+    };
+
+    let mut obj = HashMap::new();
+    for entry in entries {
+        let (key, v) = match entry {
+            AgentValue::Object(mut e) if e.contains_key("key") => {
+                let key = e.remove("key").unwrap();
+                (key, e.remove("value").unwrap_or(AgentValue::Unit))
+            }
+            AgentValue::Array(pair) if pair.len() == 2 => (pair[0].clone(), pair[1].clone()),
+            other => {
+                return Err(AgentError::InvalidValue(format!(
+                    "Invalid entry: {}",
+                    serde_json::to_string(&other).unwrap_or_default()
+                )));
+            }
+        };
+        let key = match key {
+            AgentValue::String(s) => s.to_string(),
+            other => serde_json::to_string(&other).unwrap_or_default(),
+        };
+        obj.insert(key, v);
+    }
+    Ok(AgentValue::object(obj))
+}
+
+/// Parses a dotted key path such as `items.0.name`. An empty string is the empty path.
+pub(crate) fn parse_key_path(key_str: &str) -> Vec<String> {
+    if key_str.is_empty() {
+        return Vec::new();
     }
+    key_str.split('.').map(|s| s.to_string()).collect()
 }
 
-fn get_nested_value<'a, K: AsRef<str>>(
+/// Parses a comma-separated list of dotted key paths, skipping empty entries.
+fn parse_key_paths(list: &str) -> Vec<Vec<String>> {
+    list.split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(parse_key_path)
+        .collect()
+}
+
+/// Returns the value at a key path.
+/// Keys index into objects by name and into arrays by position (negative from the end).
+pub(crate) fn get_nested_value<'a, K: AsRef<str>>(
     value: &'a AgentValue,
     keys: &[K],
 ) -> Option<&'a AgentValue> {
     let mut current_value = value;
     for key in keys {
-        let obj = current_value.as_object()?;
-        current_value = obj.get(key.as_ref())?;
+        current_value = match current_value {
+            AgentValue::Object(obj) => obj.get(key.as_ref())?,
+            AgentValue::Array(arr) => {
+                let index = key.as_ref().parse::<i64>().ok()?;
+                arr.get(resolve_index(arr.len(), index)?)?
+            }
+            _ => return None,
+        };
     }
     Some(current_value)
 }
 
+/// Sets the value at a key path, creating missing containers along the way.
+///
+/// Numeric keys index into existing arrays (negative from the end), and the index just past
+/// the end appends. A missing or non-container value is replaced with an array if the key
+/// is `0` and with an object otherwise, so `stats.404` creates `{"404": ...}`. Out-of-range
+/// indices and non-numeric keys on an existing array are ignored.
 fn set_nested_value<K: AsRef<str>>(root: &mut AgentValue, keys: &[K], new_value: AgentValue) {
-    if keys.is_empty() {
+    let Some((key, rest)) = keys.split_first() else {
         return;
+    };
+    let key = key.as_ref();
+    let index = key.parse::<i64>().ok();
+
+    match (&*root, index) {
+        (AgentValue::Object(_), _) => {}
+        (AgentValue::Array(_), _) => {}
+        (_, Some(0)) => *root = AgentValue::array_default(),
+        _ => *root = AgentValue::object_default(),
     }
 
-    // Split into the last key and the path before it
-    // keys = ["a", "b", "c"] -> path=["a", "b"], last_key="c"
-    let (last_key, path) = keys.split_last().unwrap();
-
-    let mut current = root;
+    let slot = match root {
+        AgentValue::Array(arr) => {
+            let Some(i) = index.and_then(|i| {
+                if i < 0 {
+                    resolve_index(arr.len(), i)
+                } else {
+                    Some(i as usize).filter(|&i| i <= arr.len())
+                }
+            }) else {
+                return;
+            };
+            if i == arr.len() {
+                arr.push_back(AgentValue::Unit);
+            }
+            &mut arr[i]
+        }
+        AgentValue::Object(obj) => obj.entry(key.to_string()).or_insert(AgentValue::Unit),
+        _ => unreachable!(),
+    };
+
+    if rest.is_empty() {
+        *slot = new_value;
+    } else {
+        set_nested_value(slot, rest, new_value);
+    }
+}
 
-    // Traverse down to just before the target
+/// Removes the value at a key path and returns it. Array items are removed by index.
+fn remove_nested_value<K: AsRef<str>>(root: &mut AgentValue, keys: &[K]) -> Option<AgentValue> {
+    let (last_key, path) = keys.split_last()?;
+    let mut parent = root;
     for key in path {
-        // If current position is not an Object, forcibly overwrite it with an empty Object
-        if !current.is_object() {
-            *current = AgentValue::object_default();
+        parent = match parent {
+            AgentValue::Object(obj) => obj.get_mut(key.as_ref())?,
+            AgentValue::Array(arr) => {
+                let index = key.as_ref().parse::<i64>().ok()?;
+                let i = resolve_index(arr.len(), index)?;
+                arr.get_mut(i)?
+            }
+            _ => return None,
+        };
+    }
+    match parent {
+        AgentValue::Object(obj) => obj.remove(last_key.as_ref()),
+        AgentValue::Array(arr) => {
+            let index = last_key.as_ref().parse::<i64>().ok()?;
+            let i = resolve_index(arr.len(), index)?;
+            Some(arr.remove(i))
         }
+        _ => None,
+    }
+}
 
-        let obj = current.as_object_mut().unwrap();
+/// Resolves the array indices of key paths against a value and orders the paths so that removing
+/// them one by one does not shift the positions of the others: higher array indices come first.
+/// Paths that do not exist in the value are dropped.
+fn removal_order(value: &AgentValue, paths: &[Vec<String>]) -> Vec<Vec<String>> {
+    let mut resolved: Vec<Vec<String>> = paths
+        .iter()
+        .filter_map(|keys| resolve_key_path(value, keys))
+        .collect();
+    resolved.sort_by(|a, b| {
+        b.iter()
+            .zip(a)
+            .map(|(b, a)| natural_cmp(b, a))
+            .find(|ord| ord.is_ne())
+            .unwrap_or_else(|| b.len().cmp(&a.len()))
+    });
+    resolved.dedup();
+    resolved
+}
 
-        current = obj
-            .entry(key.as_ref().to_string())
-            .or_insert_with(AgentValue::object_default);
+/// Rewrites the array indices of a key path as non-negative positions within the value.
+fn resolve_key_path(value: &AgentValue, keys: &[String]) -> Option<Vec<String>> {
+    let mut current_value = value;
+    let mut resolved = Vec::with_capacity(keys.len());
+    for key in keys {
+        current_value = match current_value {
+            AgentValue::Object(obj) => {
+                resolved.push(key.clone());
+                obj.get(key)?
+            }
+            AgentValue::Array(arr) => {
+                let i = resolve_index(arr.len(), key.parse::<i64>().ok()?)?;
+                resolved.push(i.to_string());
+                arr.get(i)?
+            }
+            _ => return None,
+        };
     }
+    Some(resolved)
+}
 
-    // Set the value for the last key
-    if !current.is_object() {
-        *current = AgentValue::object_default();
+/// Removes each key path, pruning the parents left empty if `prune` is set.
+fn delete_key_paths(mut value: AgentValue, target_keys: &[Vec<String>], prune: bool) -> AgentValue {
+    for keys in removal_order(&value, target_keys) {
+        if remove_nested_value(&mut value, &keys).is_some() && prune {
+            prune_empty_parents(&mut value, &keys);
+        }
     }
+    value
+}
 
-    if let Some(obj) = current.as_object_mut() {
-        obj.insert(last_key.as_ref().to_string(), new_value);
+/// Removes the parents of a removed key path, innermost first, while they are empty objects
+/// or arrays.
+fn prune_empty_parents<K: AsRef<str>>(root: &mut AgentValue, keys: &[K]) {
+    for depth in (1..keys.len()).rev() {
+        let parent = &keys[..depth];
+        let is_empty = match get_nested_value(root, parent) {
+            Some(AgentValue::Object(obj)) => obj.is_empty(),
+            Some(AgentValue::Array(arr)) => arr.is_empty(),
+            _ => false,
+        };
+        if !is_empty {
+            return;
+        }
+        remove_nested_value(root, parent);
     }
 }
 
@@ -615,6 +1654,254 @@ impl AsAgent for ZipToObjectAgent {
     }
 }
 
+/// Deep-merges the values arriving on n inputs (in1, in2, ...), in pin order.
+///
+/// Objects are merged key by key. Arrays are merged according to `arrays`:
+///
+/// - `replace`: arrays are values like any other (see `conflict`)
+/// - `concat`: the right array is appended to the left one
+/// - `index`: items at the same index are merged, and extra items are appended
+/// - `key`: object items with the same value at the `array_key` path are merged,
+///   and others are appended
+///
+/// When two different non-mergeable values meet, `conflict` decides: `right` (later inputs win),
+/// `left` (earlier inputs win) or `error`.
+///
+/// Inputs are paired like ZipToObject, including the `use_ctx` matching.
+#[askit_agent(
+    title = "Merge",
+    category = CATEGORY,
+    inputs = [PIN_IN1, PIN_IN2],
+    outputs = [PIN_VALUE],
+    integer_config(name = CONFIG_N, default = 2),
+    string_config(name = CONFIG_ARRAYS, default = "replace", description = "replace, concat, index or key"),
+    string_config(name = CONFIG_ARRAY_KEY, description = "key path to match array items (arrays = key)"),
+    string_config(name = CONFIG_CONFLICT, default = "right", description = "right, left or error"),
+    boolean_config(name = CONFIG_USE_CTX),
+    integer_config(name = CONFIG_TTL_SECONDS, default = 60),
+    integer_config(name = CONFIG_CAPACITY, default = 1000),
+)]
+struct MergeAgent {
+    data: AgentData,
+    n: usize,
+    use_ctx: bool,
+    ttl_sec: u64,
+    capacity: u64,
+    options: MergeOptions,
+    zip: ZipBuffer,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct MergeOptions {
+    arrays: ArrayMerge,
+    conflict: Conflict,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ArrayMerge {
+    Replace,
+    Concat,
+    Index,
+    Key(Vec<String>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Conflict {
+    Left,
+    Right,
+    Error,
+}
+
+impl MergeAgent {
+    fn update_spec(
+        spec: &mut AgentSpec,
+    ) -> Result<(usize, bool, u64, u64, MergeOptions), AgentError> {
+        let Some(cfg) = spec.configs.as_ref() else {
+            return Err(AgentError::NoConfig);
+        };
+
+        let n = cfg.get_integer_or(CONFIG_N, 2).max(1) as usize;
+
+        let arrays = match cfg.get_string_or(CONFIG_ARRAYS, "replace").as_str() {
+            "replace" => ArrayMerge::Replace,
+            "concat" => ArrayMerge::Concat,
+            "index" => ArrayMerge::Index,
+            "key" => {
+                let key_str = cfg.get_string_or_default(CONFIG_ARRAY_KEY);
+                if key_str.is_empty() {
+                    return Err(AgentError::InvalidConfig(
+                        "array_key is required when arrays is key".into(),
+                    ));
+                }
+                ArrayMerge::Key(parse_key_path(&key_str))
+            }
+            other => {
+                return Err(AgentError::InvalidConfig(format!(
+                    "Invalid arrays '{}': expected replace, concat, index or key",
+                    other
+                )));
+            }
+        };
+
+        let conflict = match cfg.get_string_or(CONFIG_CONFLICT, "right").as_str() {
+            "right" => Conflict::Right,
+            "left" => Conflict::Left,
+            "error" => Conflict::Error,
+            other => {
+                return Err(AgentError::InvalidConfig(format!(
+                    "Invalid conflict '{}': expected right, left or error",
+                    other
+                )));
+            }
+        };
+
+        let (use_ctx, ttl_sec, capacity) = zip_configs(spec);
+
+        spec.inputs = Some((1..=n).map(|i| format!("in{}", i)).collect());
+
+        Ok((
+            n,
+            use_ctx,
+            ttl_sec,
+            capacity,
+            MergeOptions { arrays, conflict },
+        ))
+    }
+}
+
+#[async_trait]
+impl AsAgent for MergeAgent {
+    fn new(askit: ASKit, id: String, mut spec: AgentSpec) -> Result<Self, AgentError> {
+        let (n, use_ctx, ttl_sec, capacity, options) = Self::update_spec(&mut spec)?;
+        let data = AgentData::new(askit, id, spec);
+        Ok(Self {
+            data,
+            n,
+            use_ctx,
+            ttl_sec,
+            capacity,
+            options,
+            zip: ZipBuffer::new(n, use_ctx, ttl_sec, capacity),
+        })
+    }
+
+    fn configs_changed(&mut self) -> Result<(), AgentError> {
+        let (n, use_ctx, ttl_sec, capacity, options) = Self::update_spec(&mut self.data.spec)?;
+        self.options = options;
+        if (n, use_ctx, ttl_sec, capacity) != (self.n, self.use_ctx, self.ttl_sec, self.capacity) {
+            let n_changed = n != self.n;
+            self.n = n;
+            self.use_ctx = use_ctx;
+            self.ttl_sec = ttl_sec;
+            self.capacity = capacity;
+            self.zip = ZipBuffer::new(n, use_ctx, ttl_sec, capacity);
+            if n_changed {
+                self.emit_agent_spec_updated();
+            }
+        }
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), AgentError> {
+        self.zip.reset();
+        Ok(())
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let Some(inputs) = self.zip.push(&ctx, &pin, value)? else {
+            return Ok(());
+        };
+
+        let mut inputs = inputs.into_iter();
+        let mut merged = inputs.next().unwrap_or(AgentValue::Unit);
+        for input in inputs {
+            merged = deep_merge(merged, input, &self.options, &mut Vec::new())?;
+        }
+
+        self.output(ctx, PIN_VALUE, merged).await
+    }
+}
+
+/// Deep-merges right into left. path tracks the current key path for conflict errors.
+fn deep_merge(
+    left: AgentValue,
+    right: AgentValue,
+    options: &MergeOptions,
+    path: &mut Vec<String>,
+) -> Result<AgentValue, AgentError> {
+    match (left, right) {
+        (AgentValue::Object(mut left), AgentValue::Object(right)) => {
+            for (key, r) in right {
+                let merged = match left.remove(&key) {
+                    Some(l) => {
+                        path.push(key.clone());
+                        let merged = deep_merge(l, r, options, path)?;
+                        path.pop();
+                        merged
+                    }
+                    None => r,
+                };
+                left.insert(key, merged);
+            }
+            Ok(AgentValue::Object(left))
+        }
+        (AgentValue::Array(mut left), AgentValue::Array(right))
+            if options.arrays != ArrayMerge::Replace =>
+        {
+            match &options.arrays {
+                ArrayMerge::Concat => left.append(right),
+                ArrayMerge::Index => {
+                    for (i, r) in right.into_iter().enumerate() {
+                        if i < left.len() {
+                            path.push(i.to_string());
+                            let merged = deep_merge(left[i].clone(), r, options, path)?;
+                            path.pop();
+                            left.set(i, merged);
+                        } else {
+                            left.push_back(r);
+                        }
+                    }
+                }
+                ArrayMerge::Key(keys) => {
+                    let item_key = |item: &AgentValue| {
+                        get_nested_value(item, keys).and_then(|k| serde_json::to_string(k).ok())
+                    };
+                    for r in right {
+                        let position = item_key(&r).and_then(|k| {
+                            left.iter().position(|l| item_key(l).as_ref() == Some(&k))
+                        });
+                        match position {
+                            Some(i) => {
+                                path.push(i.to_string());
+                                let merged = deep_merge(left[i].clone(), r, options, path)?;
+                                path.pop();
+                                left.set(i, merged);
+                            }
+                            None => left.push_back(r),
+                        }
+                    }
+                }
+                ArrayMerge::Replace => unreachable!(),
+            }
+            Ok(AgentValue::Array(left))
+        }
+        (left, right) if left == right => Ok(left),
+        (left, right) => match options.conflict {
+            Conflict::Right => Ok(right),
+            Conflict::Left => Ok(left),
+            Conflict::Error => Err(AgentError::InvalidValue(format!(
+                "Merge conflict at '{}'",
+                path.join(".")
+            ))),
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use im::hashmap;
@@ -741,4 +2028,416 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn test_get_nested_value_with_array_index() {
+        let root = AgentValue::object(hashmap! {
+            "items".to_string() => AgentValue::array(im::vector![
+                AgentValue::object(hashmap! { "name".to_string() => AgentValue::string("a") }),
+                AgentValue::object(hashmap! { "name".to_string() => AgentValue::string("b") }),
+            ]),
+        });
+
+        assert_eq!(
+            get_nested_value(&root, &["items", "0", "name"]),
+            Some(&AgentValue::string("a"))
+        );
+        assert_eq!(
+            get_nested_value(&root, &["items", "-1", "name"]),
+            Some(&AgentValue::string("b"))
+        );
+        assert_eq!(get_nested_value(&root, &["items", "2"]), None);
+        assert_eq!(get_nested_value(&root, &["items", "-3"]), None);
+        assert_eq!(get_nested_value(&root, &["items", "name"]), None);
+    }
+
+    /// Verify if arrays are indexed and auto-created when the next key is 0.
+    #[test]
+    fn test_set_nested_value_with_array_index() {
+        let mut root = AgentValue::object_default();
+
+        // Missing "items" becomes an array because the key is 0, then appends
+        set_nested_value(&mut root, &["items", "0", "name"], AgentValue::string("a"));
+        set_nested_value(&mut root, &["items", "1"], AgentValue::integer(2));
+        set_nested_value(&mut root, &["items", "1"], AgentValue::integer(3));
+        assert_eq!(
+            root.get("items"),
+            Some(&AgentValue::array(im::vector![
+                AgentValue::object(hashmap! { "name".to_string() => AgentValue::string("a") }),
+                AgentValue::integer(3),
+            ]))
+        );
+
+        // Negative indices count from the end; out of range is ignored
+        set_nested_value(&mut root, &["items", "-1"], AgentValue::integer(4));
+        set_nested_value(&mut root, &["items", "-9"], AgentValue::integer(5));
+        assert_eq!(
+            get_nested_value(&root, &["items", "1"]),
+            Some(&AgentValue::integer(4))
+        );
+        assert_eq!(
+            root.get("items")
+                .and_then(|v| v.as_array())
+                .map(|a| a.len()),
+            Some(2)
+        );
+
+        // A non-numeric key leaves the array untouched
+        set_nested_value(&mut root, &["items", "first"], AgentValue::integer(1));
+        assert_eq!(
+            root.get("items"),
+            Some(&AgentValue::array(im::vector![
+                AgentValue::object(hashmap! { "name".to_string() => AgentValue::string("a") }),
+                AgentValue::integer(4),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_set_nested_value_with_numeric_object_key() {
+        // A numeric key other than 0 on a missing value creates an object
+        let mut root = AgentValue::object_default();
+        set_nested_value(&mut root, &["stats", "404"], AgentValue::integer(1));
+        assert_eq!(
+            root.get("stats"),
+            Some(&AgentValue::object(
+                hashmap! { "404".to_string() => AgentValue::integer(1) }
+            ))
+        );
+
+        // An index past the end of an array is ignored instead of padding or replacing the array
+        let mut root = AgentValue::from_json(serde_json::json!({"items": [1]})).unwrap();
+        set_nested_value(&mut root, &["items", "1000000000"], AgentValue::integer(2));
+        assert_eq!(
+            root,
+            AgentValue::from_json(serde_json::json!({"items": [1]})).unwrap()
+        );
+
+        let flat = hashmap! {
+            "a.0".to_string() => AgentValue::integer(1),
+            "a.2".to_string() => AgentValue::integer(2),
+        };
+        assert_eq!(
+            unflatten_object(flat, "."),
+            AgentValue::from_json(serde_json::json!({"a": [1]})).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_deep_merge() {
+        let merge = |l: serde_json::Value, r: serde_json::Value, arrays, conflict| {
+            let options = MergeOptions { arrays, conflict };
+            deep_merge(
+                AgentValue::from_json(l).unwrap(),
+                AgentValue::from_json(r).unwrap(),
+                &options,
+                &mut Vec::new(),
+            )
+        };
+        let json = |v: serde_json::Value| AgentValue::from_json(v).unwrap();
+
+        let defaults = serde_json::json!({"model": "m1", "params": {"temp": 0.7, "stop": ["a"]}});
+        let overrides = serde_json::json!({"params": {"temp": 0.2, "stop": ["b"]}, "stream": true});
+
+        assert_eq!(
+            merge(
+                defaults.clone(),
+                overrides.clone(),
+                ArrayMerge::Replace,
+                Conflict::Right
+            )
+            .unwrap(),
+            json(serde_json::json!({
+                "model": "m1",
+                "params": {"temp": 0.2, "stop": ["b"]},
+                "stream": true,
+            }))
+        );
+        assert_eq!(
+            merge(
+                defaults.clone(),
+                overrides.clone(),
+                ArrayMerge::Concat,
+                Conflict::Left
+            )
+            .unwrap(),
+            json(serde_json::json!({
+                "model": "m1",
+                "params": {"temp": 0.7, "stop": ["a", "b"]},
+                "stream": true,
+            }))
+        );
+
+        let err = merge(defaults, overrides, ArrayMerge::Concat, Conflict::Error).unwrap_err();
+        assert!(err.to_string().contains("params.temp"));
+
+        assert_eq!(
+            merge(
+                serde_json::json!([{"a": 1}, {"a": 2}]),
+                serde_json::json!([{"b": 1}, {"b": 2}, {"b": 3}]),
+                ArrayMerge::Index,
+                Conflict::Right
+            )
+            .unwrap(),
+            json(serde_json::json!([{"a": 1, "b": 1}, {"a": 2, "b": 2}, {"b": 3}]))
+        );
+        assert_eq!(
+            merge(
+                serde_json::json!([{"id": 1, "x": 1}, {"id": 2, "x": 2}]),
+                serde_json::json!([{"id": 2, "x": 3}, {"id": 3, "x": 4}]),
+                ArrayMerge::Key(vec!["id".to_string()]),
+                Conflict::Right
+            )
+            .unwrap(),
+            json(serde_json::json!([{"id": 1, "x": 1}, {"id": 2, "x": 3}, {"id": 3, "x": 4}]))
+        );
+    }
+
+    #[test]
+    fn test_remove_nested_value() {
+        let mut root = AgentValue::from_json(serde_json::json!({
+            "user": {"name": "Alice", "token": "secret"},
+            "items": [1, 2, 3],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            remove_nested_value(&mut root, &["user", "token"]),
+            Some(AgentValue::string("secret"))
+        );
+        assert_eq!(
+            remove_nested_value(&mut root, &["items", "-1"]),
+            Some(AgentValue::integer(3))
+        );
+        assert_eq!(remove_nested_value(&mut root, &["user", "missing"]), None);
+        assert_eq!(remove_nested_value(&mut root, &["user", "name", "x"]), None);
+        assert_eq!(
+            root,
+            AgentValue::from_json(serde_json::json!({"user": {"name": "Alice"}, "items": [1, 2]}))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_projection() {
+        let value = AgentValue::from_json(serde_json::json!({
+            "id": 1,
+            "user": {"name": "Alice", "email": "a@example.com"},
+            "debug": true,
+        }))
+        .unwrap();
+
+        let projection = Projection {
+            pick: parse_key_paths("id, user"),
+            omit: parse_key_paths("user.email"),
+            rename: vec![(parse_key_path("user.name"), parse_key_path("name"))],
+        };
+        assert_eq!(
+            projection.apply(value),
+            AgentValue::from_json(serde_json::json!({"id": 1, "user": {}, "name": "Alice"}))
+                .unwrap()
+        );
+
+        // Non-objects pass through
+        assert_eq!(
+            projection.apply(AgentValue::integer(1)),
+            AgentValue::integer(1)
+        );
+    }
+
+    #[test]
+    fn test_projection_omit_array_indices() {
+        let value = AgentValue::from_json(serde_json::json!({
+            "items": ["a", "b", "c", "d", "e"],
+            "rows": [{"x": 1, "y": 2}, {"x": 3, "y": 4}],
+        }))
+        .unwrap();
+
+        let projection = Projection {
+            pick: Vec::new(),
+            omit: parse_key_paths("items.1, items.3, items.-1, items.1, rows.0, rows.1.y"),
+            rename: Vec::new(),
+        };
+        assert_eq!(
+            projection.apply(value),
+            AgentValue::from_json(serde_json::json!({
+                "items": ["a", "c"],
+                "rows": [{"x": 3}],
+            }))
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_prune_empty_parents() {
+        let mut root = AgentValue::from_json(serde_json::json!({
+            "auth": {"keys": {"token": "secret"}},
+            "blob": {"data": "...", "size": 3},
+        }))
+        .unwrap();
+
+        let keys = parse_key_path("auth.keys.token");
+        remove_nested_value(&mut root, &keys);
+        prune_empty_parents(&mut root, &keys);
+
+        let keys = parse_key_path("blob.data");
+        remove_nested_value(&mut root, &keys);
+        prune_empty_parents(&mut root, &keys);
+
+        assert_eq!(
+            root,
+            AgentValue::from_json(serde_json::json!({"blob": {"size": 3}})).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_delete_key_paths_array_indices() {
+        let value = AgentValue::from_json(serde_json::json!({
+            "tags": ["a", "b", "c"],
+            "rows": [{"x": 1}, {"x": 2}, {"x": 3}],
+        }))
+        .unwrap();
+
+        let target_keys = parse_key_paths("tags.0, tags.1, rows.0.x, rows.2.x");
+        assert_eq!(
+            delete_key_paths(value, &target_keys, true),
+            AgentValue::from_json(serde_json::json!({
+                "tags": ["c"],
+                "rows": [{"x": 2}],
+            }))
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+            },
+            "required": ["name"],
+        });
+        let validator = jsonschema::draft202012::new(&schema).unwrap();
+
+        let valid = AgentValue::from_json(serde_json::json!({"name": "a", "tags": ["x"]})).unwrap();
+        assert!(validation_errors(&validator, &valid).unwrap().is_empty());
+
+        let invalid = AgentValue::from_json(serde_json::json!({"tags": ["x", 1]})).unwrap();
+        let errors = validation_errors(&validator, &invalid).unwrap();
+        let paths: Vec<_> = errors
+            .iter()
+            .map(|e| e.get("path").unwrap().clone())
+            .collect();
+        assert_eq!(errors.len(), 2);
+        assert!(paths.contains(&AgentValue::string("")));
+        assert!(paths.contains(&AgentValue::string("/tags/1")));
+    }
+
+    #[test]
+    fn test_merge_patch_diff() {
+        let left = serde_json::json!({"a": 1, "b": {"c": 2, "d": 3}, "e": [1]});
+        let right = serde_json::json!({"a": 1, "b": {"c": 4, "d": 3}, "e": [1, 2], "f": true});
+
+        let patch = merge_patch_diff(&left, &right);
+        assert_eq!(
+            patch,
+            serde_json::json!({"b": {"c": 4}, "e": [1, 2], "f": true})
+        );
+
+        let mut doc = left.clone();
+        json_patch::merge(&mut doc, &patch);
+        assert_eq!(doc, right);
+
+        // Removed keys become null
+        assert_eq!(
+            merge_patch_diff(&right, &left),
+            serde_json::json!({"b": {"c": 2}, "e": [1], "f": null})
+        );
+    }
+
+    #[test]
+    fn test_flatten_and_unflatten() {
+        let nested = AgentValue::from_json(serde_json::json!({
+            "a": {"b": {"c": 1}},
+            "items": [{"name": "x"}, {"name": "y"}],
+            "empty": {},
+        }))
+        .unwrap();
+
+        let mut flat = HashMap::new();
+        flatten_value(&nested, &mut Vec::new(), ".", true, &mut flat);
+        assert_eq!(
+            AgentValue::object(flat.clone()),
+            AgentValue::from_json(serde_json::json!({
+                "a.b.c": 1,
+                "items.0.name": "x",
+                "items.1.name": "y",
+                "empty": {},
+            }))
+            .unwrap()
+        );
+        assert_eq!(unflatten_object(flat, "."), nested);
+
+        let mut flat = HashMap::new();
+        flatten_value(&nested, &mut Vec::new(), "/", false, &mut flat);
+        assert_eq!(flat.get("a/b/c"), Some(&AgentValue::integer(1)));
+        assert_eq!(flat.get("items"), nested.get("items"));
+        assert_eq!(unflatten_object(flat, "/"), nested);
+
+        // Array items are restored in index order beyond 10 items
+        let long = AgentValue::from_json(serde_json::json!({"items": (0..12).collect::<Vec<_>>()}))
+            .unwrap();
+        let mut flat = HashMap::new();
+        flatten_value(&long, &mut Vec::new(), ".", true, &mut flat);
+        assert_eq!(unflatten_object(flat, "."), long);
+    }
+
+    #[test]
+    fn test_entries_round_trip() {
+        let obj = AgentValue::from_json(serde_json::json!({
+            "b": [1, 2],
+            "a": {"x": null},
+            "c": "s",
+        }))
+        .unwrap();
+
+        let entries = object_entries(obj.clone()).unwrap();
+        assert_eq!(
+            entries,
+            AgentValue::from_json(serde_json::json!([
+                {"key": "a", "value": {"x": null}},
+                {"key": "b", "value": [1, 2]},
+                {"key": "c", "value": "s"},
+            ]))
+            .unwrap()
+        );
+        assert_eq!(from_entries(entries).unwrap(), obj);
+
+        // Pairs, non-string keys, a missing value and later entries overwriting earlier ones
+        let entries = AgentValue::from_json(serde_json::json!([
+            ["a", 1],
+            {"key": 2, "value": true},
+            {"key": "b"},
+            ["a", 3],
+        ]))
+        .unwrap();
+        assert_eq!(
+            from_entries(entries).unwrap(),
+            AgentValue::from_json(serde_json::json!({"a": 3, "2": true, "b": null})).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_entries_invalid() {
+        let invalid =
+            |json: serde_json::Value| from_entries(AgentValue::from_json(json).unwrap()).is_err();
+        assert!(invalid(serde_json::json!({"key": "a", "value": 1})));
+        assert!(invalid(serde_json::json!([["a"]])));
+        assert!(invalid(serde_json::json!([["a", 1, 2]])));
+        assert!(invalid(serde_json::json!([{"value": 1}])));
+        assert!(invalid(serde_json::json!(["a"])));
+        assert!(object_entries(AgentValue::integer(1)).is_err());
+    }
 }
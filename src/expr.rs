@@ -0,0 +1,559 @@
+//! A small expression language for predicate-driven agents.
+//!
+//! Expressions are evaluated against named variables (e.g. `value`) and support:
+//!
+//! - literals: `1`, `0.5`, `"text"`, `'text'`, `true`, `false`, `null`
+//! - member access: `value.score`, `value["key"]`, `value.items[0]`, `value.items[-1]`
+//! - arithmetic: `+`, `-`, `*`, `/`, `%` (`+` also concatenates strings)
+//! - comparison: `==`, `!=`, `<`, `<=`, `>`, `>=`
+//! - logic: `&&`, `||`, `!`, and parentheses
+//!
+//! Missing keys and out-of-range indices evaluate to `null`, and comparisons between
+//! incompatible types are simply `false`, so predicates can be applied to heterogeneous data.
+
+use agent_stream_kit::{AgentError, AgentValue};
+
+use crate::index::resolve_index;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Expr {
+    node: Node,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Literal(AgentValue),
+    Var(String),
+    Member(Box<Node>, String),
+    Index(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Neg(Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BinOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl Expr {
+    /// Parses an expression string.
+    pub(crate) fn parse(src: &str) -> Result<Self, AgentError> {
+        let tokens = tokenize(src)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let node = parser.parse_or()?;
+        if let Some(tok) = parser.peek() {
+            return Err(AgentError::InvalidConfig(format!(
+                "Unexpected token {:?} in expression '{}'",
+                tok, src
+            )));
+        }
+        Ok(Self { node })
+    }
+
+    /// Evaluates the expression. `vars` resolves identifiers such as `value` or `index`.
+    pub(crate) fn eval(&self, vars: &[(&str, &AgentValue)]) -> AgentValue {
+        eval_node(&self.node, vars)
+    }
+
+    /// Evaluates the expression and interprets the result as a boolean.
+    pub(crate) fn test(&self, vars: &[(&str, &AgentValue)]) -> bool {
+        is_truthy(&self.eval(vars))
+    }
+}
+
+/// Returns the truthiness of a value.
+/// `null`, `false`, `0`, `NaN`, empty strings, empty arrays and empty objects are false.
+pub(crate) fn is_truthy(value: &AgentValue) -> bool {
+    match value {
+        AgentValue::Unit => false,
+        AgentValue::Boolean(b) => *b,
+        AgentValue::Integer(i) => *i != 0,
+        AgentValue::Number(n) => *n != 0.0 && !n.is_nan(),
+        AgentValue::String(s) => !s.is_empty(),
+        AgentValue::Array(a) => !a.is_empty(),
+        AgentValue::Object(o) => !o.is_empty(),
+        AgentValue::Error(_) => false,
+        _ => true,
+    }
+}
+
+// Tokenizer
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Integer(i64),
+    Number(f64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+const OPERATORS: [&str; 19] = [
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "+", "-", "*", "/", "%", "(", ")", "[", "]",
+    ".",
+];
+
+fn tokenize(src: &str) -> Result<Vec<Token>, AgentError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            let mut is_float = false;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            if i + 1 < chars.len() && chars[i] == '.' && chars[i + 1].is_ascii_digit() {
+                is_float = true;
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    is_float = true;
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let invalid = || AgentError::InvalidConfig(format!("Invalid number '{}'", text));
+            if is_float {
+                tokens.push(Token::Number(text.parse().map_err(|_| invalid())?));
+            } else {
+                tokens.push(Token::Integer(text.parse().map_err(|_| invalid())?));
+            }
+        } else if c == '"' || c == '\'' {
+            let quote = c;
+            let mut s = String::new();
+            i += 1;
+            loop {
+                let Some(&ch) = chars.get(i) else {
+                    return Err(AgentError::InvalidConfig(format!(
+                        "Unterminated string in expression '{}'",
+                        src
+                    )));
+                };
+                i += 1;
+                if ch == quote {
+                    break;
+                }
+                if ch == '\\' {
+                    let Some(&esc) = chars.get(i) else {
+                        continue;
+                    };
+                    i += 1;
+                    s.push(match esc {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        other => other,
+                    });
+                } else {
+                    s.push(ch);
+                }
+            }
+            tokens.push(Token::Str(s));
+        } else if c.is_alphabetic() || c == '_' || c == '@' || c == '$' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) else {
+                return Err(AgentError::InvalidConfig(format!(
+                    "Unexpected character '{}' in expression '{}'",
+                    c, src
+                )));
+            };
+            i += op.len();
+            tokens.push(Token::Op(op));
+        }
+    }
+    Ok(tokens)
+}
+
+// Parser
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(o)) if *o == op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<(), AgentError> {
+        if self.eat_op(op) {
+            Ok(())
+        } else {
+            Err(AgentError::InvalidConfig(format!(
+                "Expected '{}' in expression",
+                op
+            )))
+        }
+    }
+
+    fn parse_binary(
+        &mut self,
+        ops: &[(&str, BinOp)],
+        next: fn(&mut Self) -> Result<Node, AgentError>,
+    ) -> Result<Node, AgentError> {
+        let mut lhs = next(self)?;
+        'outer: loop {
+            for (sym, op) in ops {
+                if self.eat_op(sym) {
+                    let rhs = next(self)?;
+                    lhs = Node::Binary(*op, Box::new(lhs), Box::new(rhs));
+                    continue 'outer;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Node, AgentError> {
+        self.parse_binary(&[("||", BinOp::Or)], Self::parse_and)
+    }
+
+    fn parse_and(&mut self) -> Result<Node, AgentError> {
+        self.parse_binary(&[("&&", BinOp::And)], Self::parse_equality)
+    }
+
+    fn parse_equality(&mut self) -> Result<Node, AgentError> {
+        self.parse_binary(
+            &[("==", BinOp::Eq), ("!=", BinOp::Ne)],
+            Self::parse_comparison,
+        )
+    }
+
+    fn parse_comparison(&mut self) -> Result<Node, AgentError> {
+        self.parse_binary(
+            &[
+                ("<=", BinOp::Le),
+                (">=", BinOp::Ge),
+                ("<", BinOp::Lt),
+                (">", BinOp::Gt),
+            ],
+            Self::parse_additive,
+        )
+    }
+
+    fn parse_additive(&mut self) -> Result<Node, AgentError> {
+        self.parse_binary(
+            &[("+", BinOp::Add), ("-", BinOp::Sub)],
+            Self::parse_multiplicative,
+        )
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Node, AgentError> {
+        self.parse_binary(
+            &[("*", BinOp::Mul), ("/", BinOp::Div), ("%", BinOp::Rem)],
+            Self::parse_unary,
+        )
+    }
+
+    fn parse_unary(&mut self) -> Result<Node, AgentError> {
+        if self.eat_op("!") {
+            return Ok(Node::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.eat_op("-") {
+            return Ok(Node::Neg(Box::new(self.parse_unary()?)));
+        }
+        self.parse_postfix()
+    }
+
+    fn parse_postfix(&mut self) -> Result<Node, AgentError> {
+        let mut node = self.parse_primary()?;
+        loop {
+            if self.eat_op(".") {
+                match self.next() {
+                    Some(Token::Ident(name)) => node = Node::Member(Box::new(node), name),
+                    Some(Token::Integer(i)) => {
+                        node = Node::Index(
+                            Box::new(node),
+                            Box::new(Node::Literal(AgentValue::integer(i))),
+                        )
+                    }
+                    _ => {
+                        return Err(AgentError::InvalidConfig(
+                            "Expected a key after '.' in expression".into(),
+                        ));
+                    }
+                }
+            } else if self.eat_op("[") {
+                let index = self.parse_or()?;
+                self.expect_op("]")?;
+                node = Node::Index(Box::new(node), Box::new(index));
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Node, AgentError> {
+        match self.next() {
+            Some(Token::Integer(i)) => Ok(Node::Literal(AgentValue::integer(i))),
+            Some(Token::Number(n)) => Ok(Node::Literal(AgentValue::number(n))),
+            Some(Token::Str(s)) => Ok(Node::Literal(AgentValue::string(s))),
+            Some(Token::Ident(name)) => Ok(match name.as_str() {
+                "true" => Node::Literal(AgentValue::boolean(true)),
+                "false" => Node::Literal(AgentValue::boolean(false)),
+                "null" => Node::Literal(AgentValue::unit()),
+                _ => Node::Var(name),
+            }),
+            Some(Token::Op("(")) => {
+                let node = self.parse_or()?;
+                self.expect_op(")")?;
+                Ok(node)
+            }
+            Some(tok) => Err(AgentError::InvalidConfig(format!(
+                "Unexpected token {:?} in expression",
+                tok
+            ))),
+            None => Err(AgentError::InvalidConfig(
+                "Unexpected end of expression".into(),
+            )),
+        }
+    }
+}
+
+// Evaluation
+
+fn eval_node(node: &Node, vars: &[(&str, &AgentValue)]) -> AgentValue {
+    match node {
+        Node::Literal(v) => v.clone(),
+        Node::Var(name) => vars
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| (*v).clone())
+            .unwrap_or(AgentValue::Unit),
+        Node::Member(target, key) => eval_node(target, vars)
+            .get(key)
+            .cloned()
+            .unwrap_or(AgentValue::Unit),
+        Node::Index(target, index) => {
+            let target = eval_node(target, vars);
+            let index = eval_node(index, vars);
+            index_value(&target, &index).unwrap_or(AgentValue::Unit)
+        }
+        Node::Not(inner) => AgentValue::boolean(!is_truthy(&eval_node(inner, vars))),
+        Node::Neg(inner) => match eval_node(inner, vars) {
+            AgentValue::Integer(i) => AgentValue::integer(i.wrapping_neg()),
+            AgentValue::Number(n) => AgentValue::number(-n),
+            _ => AgentValue::Unit,
+        },
+        Node::Binary(BinOp::And, lhs, rhs) => AgentValue::boolean(
+            is_truthy(&eval_node(lhs, vars)) && is_truthy(&eval_node(rhs, vars)),
+        ),
+        Node::Binary(BinOp::Or, lhs, rhs) => AgentValue::boolean(
+            is_truthy(&eval_node(lhs, vars)) || is_truthy(&eval_node(rhs, vars)),
+        ),
+        Node::Binary(op, lhs, rhs) => {
+            let lhs = eval_node(lhs, vars);
+            let rhs = eval_node(rhs, vars);
+            eval_binary(*op, &lhs, &rhs)
+        }
+    }
+}
+
+fn index_value(target: &AgentValue, index: &AgentValue) -> Option<AgentValue> {
+    match (target, index) {
+        (AgentValue::Array(arr), AgentValue::Integer(i)) => {
+            arr.get(resolve_index(arr.len(), *i)?).cloned()
+        }
+        (AgentValue::Object(_), AgentValue::String(key)) => target.get(key).cloned(),
+        _ => None,
+    }
+}
+
+fn eval_binary(op: BinOp, lhs: &AgentValue, rhs: &AgentValue) -> AgentValue {
+    match op {
+        BinOp::Eq => AgentValue::boolean(values_equal(lhs, rhs)),
+        BinOp::Ne => AgentValue::boolean(!values_equal(lhs, rhs)),
+        BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+            let Some(ord) = compare_values(lhs, rhs) else {
+                return AgentValue::boolean(false);
+            };
+            AgentValue::boolean(match op {
+                BinOp::Lt => ord.is_lt(),
+                BinOp::Le => ord.is_le(),
+                BinOp::Gt => ord.is_gt(),
+                _ => ord.is_ge(),
+            })
+        }
+        BinOp::Add => match (lhs, rhs) {
+            (AgentValue::String(a), AgentValue::String(b)) => {
+                AgentValue::string(format!("{}{}", a, b))
+            }
+            _ => arithmetic(lhs, rhs, i64::checked_add, |a, b| a + b),
+        },
+        BinOp::Sub => arithmetic(lhs, rhs, i64::checked_sub, |a, b| a - b),
+        BinOp::Mul => arithmetic(lhs, rhs, i64::checked_mul, |a, b| a * b),
+        BinOp::Div => match (lhs.as_f64(), rhs.as_f64()) {
+            (Some(a), Some(b)) => AgentValue::number(a / b),
+            _ => AgentValue::Unit,
+        },
+        BinOp::Rem => arithmetic(lhs, rhs, i64::checked_rem, |a, b| a % b),
+        BinOp::And | BinOp::Or => unreachable!("logical operators are short-circuited"),
+    }
+}
+
+fn arithmetic(
+    lhs: &AgentValue,
+    rhs: &AgentValue,
+    int_op: fn(i64, i64) -> Option<i64>,
+    float_op: fn(f64, f64) -> f64,
+) -> AgentValue {
+    if let (AgentValue::Integer(a), AgentValue::Integer(b)) = (lhs, rhs)
+        && let Some(v) = int_op(*a, *b)
+    {
+        return AgentValue::integer(v);
+    }
+    match (lhs.as_f64(), rhs.as_f64()) {
+        (Some(a), Some(b)) => AgentValue::number(float_op(a, b)),
+        _ => AgentValue::Unit,
+    }
+}
+
+/// Equality that treats integers and numbers as comparable.
+fn values_equal(lhs: &AgentValue, rhs: &AgentValue) -> bool {
+    match (lhs, rhs) {
+        (AgentValue::Integer(_), AgentValue::Number(_))
+        | (AgentValue::Number(_), AgentValue::Integer(_)) => lhs.as_f64() == rhs.as_f64(),
+        _ => lhs == rhs,
+    }
+}
+
+/// Orders numbers numerically and strings lexicographically. Other combinations are unordered.
+fn compare_values(lhs: &AgentValue, rhs: &AgentValue) -> Option<std::cmp::Ordering> {
+    match (lhs, rhs) {
+        (AgentValue::Integer(a), AgentValue::Integer(b)) => Some(a.cmp(b)),
+        (AgentValue::String(a), AgentValue::String(b)) => Some(a.cmp(b)),
+        (AgentValue::Boolean(a), AgentValue::Boolean(b)) => Some(a.cmp(b)),
+        _ => match (lhs, rhs) {
+            (
+                AgentValue::Integer(_) | AgentValue::Number(_),
+                AgentValue::Integer(_) | AgentValue::Number(_),
+            ) => lhs.as_f64()?.partial_cmp(&rhs.as_f64()?),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use im::{hashmap, vector};
+
+    use super::*;
+
+    fn eval(src: &str, value: &AgentValue) -> AgentValue {
+        Expr::parse(src).unwrap().eval(&[("value", value)])
+    }
+
+    #[test]
+    fn test_predicate_on_object() {
+        let value = AgentValue::object(hashmap! {
+            "score".to_string() => AgentValue::number(0.7),
+            "lang".to_string() => AgentValue::string("en"),
+        });
+        let expr = Expr::parse(r#"value.score > 0.5 && value.lang == "en""#).unwrap();
+        assert!(expr.test(&[("value", &value)]));
+
+        let expr = Expr::parse("value.score > 0.5 && value.lang == 'ja'").unwrap();
+        assert!(!expr.test(&[("value", &value)]));
+
+        // Missing keys are null and never satisfy an ordering comparison
+        let expr = Expr::parse("value.missing > 0").unwrap();
+        assert!(!expr.test(&[("value", &value)]));
+        assert!(
+            Expr::parse("value.missing == null")
+                .unwrap()
+                .test(&[("value", &value)])
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_and_precedence() {
+        let unit = AgentValue::unit();
+        assert_eq!(eval("1 + 2 * 3", &unit), AgentValue::integer(7));
+        assert_eq!(eval("(1 + 2) * 3", &unit), AgentValue::integer(9));
+        assert_eq!(eval("7 / 2", &unit), AgentValue::number(3.5));
+        assert_eq!(eval("7 % 4 - -1", &unit), AgentValue::integer(4));
+        assert_eq!(eval("'a' + \"b\"", &unit), AgentValue::string("ab"));
+        assert_eq!(eval("1 == 1.0", &unit), AgentValue::boolean(true));
+        assert_eq!(eval("!(1 < 2) || 2 >= 2", &unit), AgentValue::boolean(true));
+    }
+
+    #[test]
+    fn test_index_access() {
+        let value = AgentValue::object(hashmap! {
+            "items".to_string() => AgentValue::array(vector![
+                AgentValue::integer(10),
+                AgentValue::integer(20),
+                AgentValue::integer(30),
+            ]),
+        });
+        assert_eq!(eval("value.items[0]", &value), AgentValue::integer(10));
+        assert_eq!(eval("value.items.1", &value), AgentValue::integer(20));
+        assert_eq!(eval("value.items[-1]", &value), AgentValue::integer(30));
+        assert_eq!(eval("value.items[5]", &value), AgentValue::unit());
+        assert_eq!(
+            eval("value['items'][1 + 1]", &value),
+            AgentValue::integer(30)
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Expr::parse("value >").is_err());
+        assert!(Expr::parse("(1 + 2").is_err());
+        assert!(Expr::parse("'open").is_err());
+        assert!(Expr::parse("1 2").is_err());
+        assert!(Expr::parse("value # 1").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+//! Array index and slice resolution shared by the agents, queries and expressions.
+//!
+//! Indices and slice bounds may be negative to count from the end of the array.
+
+/// Resolves an array index (negative from the end) to a position within len.
+pub(crate) fn resolve_index(len: usize, index: i64) -> Option<usize> {
+    let i = if index < 0 {
+        len.checked_sub(index.unsigned_abs() as usize)?
+    } else {
+        index as usize
+    };
+    (i < len).then_some(i)
+}
+
+/// Resolves slice bounds (negative from the end) to a clamped index range for an array of length len.
+pub(crate) fn resolve_slice(len: usize, start: Option<i64>, end: Option<i64>) -> (usize, usize) {
+    let resolve = |bound: i64| -> usize {
+        if bound < 0 {
+            len.saturating_sub(bound.unsigned_abs() as usize)
+        } else {
+            (bound as usize).min(len)
+        }
+    };
+    let start = start.map(resolve).unwrap_or(0);
+    let end = end.map(resolve).unwrap_or(len);
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_index() {
+        assert_eq!(resolve_index(3, 0), Some(0));
+        assert_eq!(resolve_index(3, 2), Some(2));
+        assert_eq!(resolve_index(3, 3), None);
+        assert_eq!(resolve_index(3, -1), Some(2));
+        assert_eq!(resolve_index(3, -3), Some(0));
+        assert_eq!(resolve_index(3, -4), None);
+        assert_eq!(resolve_index(0, 0), None);
+    }
+
+    #[test]
+    fn test_resolve_slice() {
+        assert_eq!(resolve_slice(10, Some(-5), None), (5, 10));
+        assert_eq!(resolve_slice(3, Some(-5), None), (0, 3));
+        assert_eq!(resolve_slice(10, Some(2), Some(-2)), (2, 8));
+        assert_eq!(resolve_slice(10, Some(20), None), (10, 10));
+        assert_eq!(resolve_slice(10, None, Some(3)), (0, 3));
+    }
+}
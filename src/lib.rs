@@ -16,3 +16,7 @@ pub mod image;
 
 #[cfg(feature = "yaml")]
 pub mod yaml;
+
+mod expr;
+mod index;
+mod query;
@@ -0,0 +1,405 @@
+//! JSON Pointer and JSONPath queries over `AgentValue`s.
+//!
+//! A query starting with `$` is a JSONPath (subset) and anything else is a JSON Pointer (RFC 6901),
+//! e.g. `/items/0/name` (`~1` escapes `/` and `~0` escapes `~`; the empty pointer is the whole value).
+//!
+//! Supported JSONPath syntax:
+//!
+//! - `$`: the root value
+//! - `.name`, `['name']`, `["name"]`: object member
+//! - `[0]`, `[-1]`: array index (negative from the end)
+//! - `[1:3]`, `[-2:]`: array slice
+//! - `.*`, `[*]`: all array items or object values (objects in key order)
+//! - `..name`, `..*`, `..[0]`: the selector applied to the value and all of its descendants
+//! - `[?(@.score > 0.5)]`: array items or object values for which an expression is true,
+//!   with `@` bound to the item and `$` to the root (see `expr` for the syntax)
+
+use agent_stream_kit::{AgentError, AgentValue};
+
+use crate::expr::Expr;
+use crate::index::{resolve_index, resolve_slice};
+
+#[derive(Clone, Debug)]
+pub(crate) enum Query {
+    Pointer(Vec<String>),
+    Path(Vec<Segment>),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Segment {
+    Child(Selector),
+    Descendant(Selector),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Selector {
+    Name(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Wildcard,
+    Filter(Expr),
+}
+
+impl Query {
+    /// Parses a JSON Pointer or JSONPath query.
+    pub(crate) fn parse(src: &str) -> Result<Self, AgentError> {
+        let src = src.trim();
+        if src.starts_with('$') {
+            parse_path(src).map(Query::Path)
+        } else {
+            parse_pointer(src).map(Query::Pointer)
+        }
+    }
+
+    /// Returns true if the query selects at most one value (no wildcards, slices, filters or descendants).
+    pub(crate) fn is_definite(&self) -> bool {
+        match self {
+            Query::Pointer(_) => true,
+            Query::Path(segments) => segments.iter().all(|seg| {
+                matches!(
+                    seg,
+                    Segment::Child(Selector::Name(_)) | Segment::Child(Selector::Index(_))
+                )
+            }),
+        }
+    }
+
+    /// Returns the values selected by the query, in document order.
+    pub(crate) fn select<'a>(&self, root: &'a AgentValue) -> Vec<&'a AgentValue> {
+        match self {
+            Query::Pointer(tokens) => {
+                let mut current = root;
+                for token in tokens {
+                    let next = match current {
+                        AgentValue::Object(obj) => obj.get(token),
+                        AgentValue::Array(arr) => parse_array_index(token).and_then(|i| arr.get(i)),
+                        _ => None,
+                    };
+                    match next {
+                        Some(v) => current = v,
+                        None => return Vec::new(),
+                    }
+                }
+                vec![current]
+            }
+            Query::Path(segments) => {
+                let mut nodes = vec![root];
+                for segment in segments {
+                    let mut next = Vec::new();
+                    for node in nodes {
+                        match segment {
+                            Segment::Child(selector) => {
+                                apply_selector(selector, node, root, &mut next)
+                            }
+                            Segment::Descendant(selector) => {
+                                let mut descendants = Vec::new();
+                                collect_descendants(node, &mut descendants);
+                                for d in descendants {
+                                    apply_selector(selector, d, root, &mut next);
+                                }
+                            }
+                        }
+                    }
+                    nodes = next;
+                }
+                nodes
+            }
+        }
+    }
+}
+
+fn parse_pointer(src: &str) -> Result<Vec<String>, AgentError> {
+    if src.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = src.strip_prefix('/') else {
+        return Err(AgentError::InvalidConfig(format!(
+            "Invalid query '{}': expected a JSON Pointer starting with '/' or a JSONPath starting with '$'",
+            src
+        )));
+    };
+    Ok(rest
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+/// Parses an RFC 6901 array index: digits without leading zeros.
+fn parse_array_index(token: &str) -> Option<usize> {
+    if token.is_empty()
+        || !token.bytes().all(|b| b.is_ascii_digit())
+        || (token.len() > 1 && token.starts_with('0'))
+    {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn parse_path(src: &str) -> Result<Vec<Segment>, AgentError> {
+    let invalid =
+        |msg: &str| AgentError::InvalidConfig(format!("Invalid query '{}': {}", src, msg));
+
+    let chars: Vec<char> = src.chars().collect();
+    let mut segments = Vec::new();
+    let mut i = 1; // skip '$'
+    while i < chars.len() {
+        let descendant = chars[i..].starts_with(&['.', '.']);
+        if descendant {
+            i += 2;
+        } else if chars[i] == '.' {
+            i += 1;
+        } else if chars[i] != '[' {
+            return Err(invalid("expected '.' or '['"));
+        }
+
+        let selector = if chars.get(i) == Some(&'[') {
+            let end = find_bracket_end(&chars, i).ok_or_else(|| invalid("unclosed '['"))?;
+            let inner: String = chars[i + 1..end].iter().collect();
+            i = end + 1;
+            parse_bracket(inner.trim()).map_err(|e| invalid(&e))?
+        } else if chars.get(i) == Some(&'*') {
+            i += 1;
+            Selector::Wildcard
+        } else {
+            let start = i;
+            while i < chars.len() && chars[i] != '.' && chars[i] != '[' {
+                i += 1;
+            }
+            if start == i {
+                return Err(invalid("expected a member name"));
+            }
+            Selector::Name(chars[start..i].iter().collect())
+        };
+
+        segments.push(if descendant {
+            Segment::Descendant(selector)
+        } else {
+            Segment::Child(selector)
+        });
+    }
+    Ok(segments)
+}
+
+/// Returns the position of the ']' closing the '[' at start, skipping quoted strings and nested brackets.
+fn find_bracket_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut i = start;
+    while i < chars.len() {
+        let c = chars[i];
+        match quote {
+            Some(_) if c == '\\' => i += 1,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '[' => depth += 1,
+            None if c == ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            None => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Strips one pair of parentheses if it encloses the whole filter, as in `?(@.a > 1)`,
+/// but not `?(@.a > 1) || (@.b < 2)`. Parentheses in quoted strings are ignored.
+fn strip_enclosing_parens(filter: &str) -> &str {
+    let Some(inner) = filter.strip_prefix('(').and_then(|f| f.strip_suffix(')')) else {
+        return filter;
+    };
+    let mut depth = 0;
+    let mut quote = None;
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(_) if c == '\\' => {
+                chars.next();
+            }
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '(' => depth += 1,
+            None if c == ')' => {
+                if depth == 0 {
+                    // The opening paren closes before the end of the filter
+                    return filter;
+                }
+                depth -= 1;
+            }
+            None => {}
+        }
+    }
+    inner
+}
+
+fn parse_bracket(inner: &str) -> Result<Selector, String> {
+    if inner == "*" {
+        return Ok(Selector::Wildcard);
+    }
+    if let Some(filter) = inner.strip_prefix('?') {
+        return Expr::parse(strip_enclosing_parens(filter.trim()))
+            .map(Selector::Filter)
+            .map_err(|e| e.to_string());
+    }
+    if inner.len() >= 2
+        && ((inner.starts_with('\'') && inner.ends_with('\''))
+            || (inner.starts_with('"') && inner.ends_with('"')))
+    {
+        return Ok(Selector::Name(inner[1..inner.len() - 1].to_string()));
+    }
+    if let Some((start, end)) = inner.split_once(':') {
+        let bound = |s: &str| -> Result<Option<i64>, String> {
+            let s = s.trim();
+            if s.is_empty() {
+                return Ok(None);
+            }
+            s.parse()
+                .map(Some)
+                .map_err(|_| format!("invalid slice '{}'", inner))
+        };
+        return Ok(Selector::Slice(bound(start)?, bound(end)?));
+    }
+    inner
+        .parse()
+        .map(Selector::Index)
+        .map_err(|_| format!("invalid selector '[{}]'", inner))
+}
+
+/// Pushes the value and all values nested in it, in document order.
+fn collect_descendants<'a>(value: &'a AgentValue, out: &mut Vec<&'a AgentValue>) {
+    out.push(value);
+    for child in children(value) {
+        collect_descendants(child, out);
+    }
+}
+
+/// Returns array items, or object values in key order.
+fn children(value: &AgentValue) -> Vec<&AgentValue> {
+    match value {
+        AgentValue::Array(arr) => arr.iter().collect(),
+        AgentValue::Object(obj) => {
+            let mut entries: Vec<_> = obj.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            entries.into_iter().map(|(_, v)| v).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn apply_selector<'a>(
+    selector: &Selector,
+    node: &'a AgentValue,
+    root: &AgentValue,
+    out: &mut Vec<&'a AgentValue>,
+) {
+    match selector {
+        Selector::Name(name) => {
+            if let AgentValue::Object(obj) = node
+                && let Some(v) = obj.get(name)
+            {
+                out.push(v);
+            }
+        }
+        Selector::Index(index) => {
+            if let AgentValue::Array(arr) = node
+                && let Some(v) = resolve_index(arr.len(), *index).and_then(|i| arr.get(i))
+            {
+                out.push(v);
+            }
+        }
+        Selector::Slice(start, end) => {
+            if let AgentValue::Array(arr) = node {
+                let (start, end) = resolve_slice(arr.len(), *start, *end);
+                out.extend(arr.iter().skip(start).take(end.saturating_sub(start)));
+            }
+        }
+        Selector::Wildcard => out.extend(children(node)),
+        Selector::Filter(expr) => out.extend(
+            children(node)
+                .into_iter()
+                .filter(|child| expr.test(&[("@", child), ("$", root)])),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use im::{hashmap, vector};
+
+    use super::*;
+
+    fn doc() -> AgentValue {
+        let item = |name: &str, score: f64| {
+            AgentValue::object(hashmap! {
+                "name".to_string() => AgentValue::string(name),
+                "score".to_string() => AgentValue::number(score),
+            })
+        };
+        AgentValue::object(hashmap! {
+            "items".to_string() => AgentValue::array(vector![
+                item("a", 0.9),
+                item("b", 0.2),
+                item("c", 0.6),
+            ]),
+            "a/b".to_string() => AgentValue::integer(1),
+        })
+    }
+
+    fn names(query: &str) -> Vec<AgentValue> {
+        let doc = doc();
+        let selected = Query::parse(query).unwrap().select(&doc);
+        selected.into_iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_pointer() {
+        assert_eq!(names("/items/1/name"), vec![AgentValue::string("b")]);
+        assert_eq!(names("/a~1b"), vec![AgentValue::integer(1)]);
+        assert_eq!(names(""), vec![doc()]);
+        assert!(names("/items/01").is_empty());
+        assert!(names("/items/-").is_empty());
+        assert!(names("/missing").is_empty());
+        assert!(Query::parse("items").is_err());
+    }
+
+    #[test]
+    fn test_path() {
+        let strings = |xs: &[&str]| {
+            xs.iter()
+                .map(|&x| AgentValue::string(x))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names("$.items[*].name"), strings(&["a", "b", "c"]));
+        assert_eq!(names("$.items[-1].name"), strings(&["c"]));
+        assert_eq!(names("$['items'][0:2].name"), strings(&["a", "b"]));
+        assert_eq!(names("$..name"), strings(&["a", "b", "c"]));
+        assert_eq!(
+            names("$.items[?(@.score > 0.5)].name"),
+            strings(&["a", "c"])
+        );
+        assert_eq!(
+            names("$.items[?(@.score > 0.8) || (@.name == 'c')].name"),
+            strings(&["a", "c"])
+        );
+        assert_eq!(
+            names("$.items[?((@.score > 0.8) || @.name == ')')].name"),
+            strings(&["a"])
+        );
+        assert_eq!(
+            names("$.items[?@.name == 'b'].score"),
+            vec![AgentValue::number(0.2)]
+        );
+        assert!(names("$.items[5]").is_empty());
+
+        assert!(Query::parse("$.items[0].name").unwrap().is_definite());
+        assert!(!Query::parse("$.items[*]").unwrap().is_definite());
+        assert!(Query::parse("$.items[").is_err());
+        assert!(Query::parse("$.items[?(@.score >)]").is_err());
+    }
+}
@@ -10,6 +10,17 @@ const CATEGORY: &str = "Std/Utils";
 const PIN_IN: &str = "in";
 const PIN_RESET: &str = "reset";
 const PIN_COUNT: &str = "count";
+const PIN_VALUE: &str = "value";
+
+const PIN_UNIT: &str = "unit";
+const PIN_BOOLEAN: &str = "boolean";
+const PIN_INTEGER: &str = "integer";
+const PIN_NUMBER: &str = "number";
+const PIN_STRING: &str = "string";
+const PIN_IMAGE: &str = "image";
+const PIN_ARRAY: &str = "array";
+const PIN_OBJECT: &str = "object";
+const PIN_OTHER: &str = "other";
 
 const DISPLAY_COUNT: &str = "count";
 
@@ -65,3 +76,54 @@ impl AsAgent for CounterAgent {
         Ok(())
     }
 }
+
+/// Routes the input to the output pin named after its type.
+/// Values of any other type (tensors, messages, errors) go to `other`.
+#[askit_agent(
+    title = "TypeOf",
+    category = CATEGORY,
+    inputs = [PIN_VALUE],
+    outputs = [
+        PIN_UNIT,
+        PIN_BOOLEAN,
+        PIN_INTEGER,
+        PIN_NUMBER,
+        PIN_STRING,
+        PIN_IMAGE,
+        PIN_ARRAY,
+        PIN_OBJECT,
+        PIN_OTHER
+    ],
+)]
+struct TypeOfAgent {
+    data: AgentData,
+}
+
+#[async_trait]
+impl AsAgent for TypeOfAgent {
+    fn new(askit: ASKit, id: String, spec: AgentSpec) -> Result<Self, AgentError> {
+        Ok(Self {
+            data: AgentData::new(askit, id, spec),
+        })
+    }
+
+    async fn process(
+        &mut self,
+        ctx: AgentContext,
+        _pin: String,
+        value: AgentValue,
+    ) -> Result<(), AgentError> {
+        let pin = match &value {
+            AgentValue::Unit => PIN_UNIT,
+            AgentValue::Boolean(_) => PIN_BOOLEAN,
+            AgentValue::Integer(_) => PIN_INTEGER,
+            AgentValue::Number(_) => PIN_NUMBER,
+            AgentValue::String(_) => PIN_STRING,
+            AgentValue::Image(_) => PIN_IMAGE,
+            AgentValue::Array(_) => PIN_ARRAY,
+            AgentValue::Object(_) => PIN_OBJECT,
+            _ => PIN_OTHER,
+        };
+        self.output(ctx, pin, value).await
+    }
+}
@@ -3,5 +3,7 @@
 extern crate askit_std_agents;
 
 mod suites {
+    mod array_test;
+    mod data_test;
     mod input_test;
 }
@@ -0,0 +1,274 @@
+extern crate agent_stream_kit as askit;
+
+use std::time::Duration;
+
+use askit::{AgentValue, test_utils};
+use im::vector;
+
+#[tokio::test]
+async fn test_zip_to_array_use_ctx() {
+    let askit = test_utils::setup_askit().await;
+
+    let stream_id = test_utils::load_and_start_stream(&askit, "tests/streams/Std_Array_test.json")
+        .await
+        .unwrap();
+
+    // Mapped items reach both pins with the same map frame and zip per item.
+    let input = AgentValue::array(vector![AgentValue::integer(1), AgentValue::integer(2)]);
+    askit
+        .write_var_value(&stream_id, "zip_in", input.clone())
+        .await
+        .unwrap();
+    test_utils::expect_var_value(&stream_id, "zip_in", &input)
+        .await
+        .unwrap();
+    for i in 1..=2 {
+        let pair = AgentValue::array(vector![AgentValue::integer(i), AgentValue::integer(i)]);
+        test_utils::expect_var_value(&stream_id, "zip_out", &pair)
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_map_ack_collect() {
+    let askit = test_utils::setup_askit().await;
+
+    let stream_id = test_utils::load_and_start_stream(&askit, "tests/streams/Std_Array_test.json")
+        .await
+        .unwrap();
+
+    // max_in_flight = 1 with 3 items: each mapped item acks its own slot, so the
+    // fan-out is emitted one by one and Collect completes. Collect's output on ack
+    // releases only the slots of its own fan-out.
+    for input in [vector![1, 2, 3], vector![4, 5]] {
+        let input = AgentValue::array(input.into_iter().map(AgentValue::integer).collect());
+        askit
+            .write_var_value(&stream_id, "map_in", input.clone())
+            .await
+            .unwrap();
+        test_utils::expect_var_value(&stream_id, "map_in", &input)
+            .await
+            .unwrap();
+        test_utils::expect_var_value(&stream_id, "map_out", &input)
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_map_ack_nested_collect() {
+    let askit = test_utils::setup_askit().await;
+
+    let stream_id = test_utils::load_and_start_stream(&askit, "tests/streams/Std_Array_test.json")
+        .await
+        .unwrap();
+
+    // The inner Collect's output still carries the outer map frame, but it acks the
+    // whole inner fan-out, so the inner Map (max_in_flight = 2) moves on to the next row.
+    let row = |xs: &[i64]| array(xs.iter().map(|&x| AgentValue::integer(x)).collect());
+    let input = array(vec![row(&[1, 2]), row(&[3, 4]), row(&[5, 6])]);
+    askit
+        .write_var_value(&stream_id, "nested_in", input.clone())
+        .await
+        .unwrap();
+    test_utils::expect_var_value(&stream_id, "nested_in", &input)
+        .await
+        .unwrap();
+    test_utils::expect_var_value(&stream_id, "nested_out", &input)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_map_ack_collect_by_fan_out() {
+    let askit = test_utils::setup_askit().await;
+
+    let stream_id = test_utils::load_and_start_stream(&askit, "tests/streams/Std_Array_test.json")
+        .await
+        .unwrap();
+
+    // max_in_flight = 1 counts fan-outs: all items of an array are emitted together,
+    // so Collect completes even though the array is larger than the limit, and its
+    // output on ack releases the fan-out for the next array.
+    for input in [vector![1, 2, 3], vector![4, 5, 6]] {
+        let input = AgentValue::array(input.into_iter().map(AgentValue::integer).collect());
+        askit
+            .write_var_value(&stream_id, "fan_out_in", input.clone())
+            .await
+            .unwrap();
+        test_utils::expect_var_value(&stream_id, "fan_out_in", &input)
+            .await
+            .unwrap();
+        test_utils::expect_var_value(&stream_id, "fan_out_out", &input)
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_collect() {
+    let askit = test_utils::setup_askit().await;
+
+    let stream_id = test_utils::load_and_start_stream(&askit, "tests/streams/Std_Array_test.json")
+        .await
+        .unwrap();
+
+    let input = array(vec![AgentValue::integer(1), AgentValue::integer(2)]);
+    askit
+        .write_var_value(&stream_id, "collect_in", input.clone())
+        .await
+        .unwrap();
+    test_utils::expect_var_value(&stream_id, "collect_in", &input)
+        .await
+        .unwrap();
+    test_utils::expect_var_value(&stream_id, "collect_out", &input)
+        .await
+        .unwrap();
+
+    // Strings are delayed, so the two fan-outs reach Collect as 1, 2, "a", "b".
+    let input1 = array(vec![AgentValue::integer(1), AgentValue::string("a")]);
+    let input2 = array(vec![AgentValue::integer(2), AgentValue::string("b")]);
+    for input in [&input1, &input2] {
+        askit
+            .write_var_value(&stream_id, "collect_in", input.clone())
+            .await
+            .unwrap();
+        test_utils::expect_var_value(&stream_id, "collect_in", input)
+            .await
+            .unwrap();
+    }
+    for input in [&input1, &input2] {
+        test_utils::expect_var_value(&stream_id, "collect_out", input)
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_collect_evict() {
+    let askit = test_utils::setup_askit().await;
+
+    let stream_id = test_utils::load_and_start_stream(&askit, "tests/streams/Std_Array_test.json")
+        .await
+        .unwrap();
+
+    // capacity = 1: the second fan-out evicts the first, whose missing index is reported
+    // once, and the first fan-out's late string is dropped instead of starting over.
+    let input1 = array(vec![AgentValue::integer(1), AgentValue::string("a")]);
+    let input2 = array(vec![AgentValue::integer(2), AgentValue::string("b")]);
+    for input in [&input1, &input2] {
+        askit
+            .write_var_value(&stream_id, "evict_in", input.clone())
+            .await
+            .unwrap();
+        test_utils::expect_var_value(&stream_id, "evict_in", input)
+            .await
+            .unwrap();
+    }
+    test_utils::expect_var_value(
+        &stream_id,
+        "evict_missing",
+        &array(vec![AgentValue::integer(1)]),
+    )
+    .await
+    .unwrap();
+    test_utils::expect_var_value(&stream_id, "evict_out", &input2)
+        .await
+        .unwrap();
+    assert!(
+        test_utils::recv_board_with_timeout(Duration::from_millis(300))
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_collect_timeout() {
+    let askit = test_utils::setup_askit().await;
+
+    let stream_id = test_utils::load_and_start_stream(&askit, "tests/streams/Std_Array_test.json")
+        .await
+        .unwrap();
+
+    // The string arrives after the timeout: the partial array and the missing
+    // indices are emitted once, and the late item is dropped.
+    let input = array(vec![
+        AgentValue::integer(1),
+        AgentValue::string("a"),
+        AgentValue::integer(3),
+    ]);
+    askit
+        .write_var_value(&stream_id, "timeout_in", input.clone())
+        .await
+        .unwrap();
+    test_utils::expect_var_value(&stream_id, "timeout_in", &input)
+        .await
+        .unwrap();
+
+    let mut events = Vec::new();
+    for _ in 0..2 {
+        events.push(
+            test_utils::recv_board_with_timeout(Duration::from_secs(1))
+                .await
+                .unwrap(),
+        );
+    }
+    events.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        events,
+        vec![
+            (
+                format!("%{}/timeout_missing", stream_id),
+                array(vec![AgentValue::integer(1)])
+            ),
+            (
+                format!("%{}/timeout_out", stream_id),
+                array(vec![
+                    AgentValue::integer(1),
+                    AgentValue::unit(),
+                    AgentValue::integer(3)
+                ])
+            ),
+        ]
+    );
+
+    assert!(
+        test_utils::recv_board_with_timeout(Duration::from_millis(500))
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_collect_ttl() {
+    let askit = test_utils::setup_askit().await;
+
+    let stream_id = test_utils::load_and_start_stream(&askit, "tests/streams/Std_Array_test.json")
+        .await
+        .unwrap();
+
+    // Without a timeout, the incomplete collection still reports its missing
+    // indices when it expires after ttl_sec.
+    let input = array(vec![AgentValue::integer(1), AgentValue::string("a")]);
+    askit
+        .write_var_value(&stream_id, "ttl_in", input.clone())
+        .await
+        .unwrap();
+    test_utils::expect_var_value(&stream_id, "ttl_in", &input)
+        .await
+        .unwrap();
+    assert_eq!(
+        test_utils::recv_board_with_timeout(Duration::from_secs(2))
+            .await
+            .unwrap(),
+        (
+            format!("%{}/ttl_missing", stream_id),
+            array(vec![AgentValue::integer(1)])
+        )
+    );
+}
+
+fn array(values: Vec<AgentValue>) -> AgentValue {
+    AgentValue::array(values.into_iter().collect())
+}
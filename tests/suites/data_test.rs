@@ -0,0 +1,36 @@
+extern crate agent_stream_kit as askit;
+
+use askit::{AgentValue, test_utils};
+
+#[tokio::test]
+async fn test_delete_value() {
+    let askit = test_utils::setup_askit().await;
+
+    let stream_id = test_utils::load_and_start_stream(&askit, "tests/streams/Std_Data_test.json")
+        .await
+        .unwrap();
+
+    // Key paths are removed from each record of an array
+    let input = AgentValue::from_json(serde_json::json!([
+        {"name": "a", "auth": {"token": "x"}, "tags": ["t"]},
+        {"name": "b", "auth": {"token": "y", "user": "u"}},
+        1,
+    ]))
+    .unwrap();
+    askit
+        .write_var_value(&stream_id, "delete_in", input.clone())
+        .await
+        .unwrap();
+    test_utils::expect_var_value(&stream_id, "delete_in", &input)
+        .await
+        .unwrap();
+    let expected = AgentValue::from_json(serde_json::json!([
+        {"name": "a"},
+        {"name": "b", "auth": {"user": "u"}},
+        1,
+    ]))
+    .unwrap();
+    test_utils::expect_var_value(&stream_id, "delete_out", &expected)
+        .await
+        .unwrap();
+}